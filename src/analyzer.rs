@@ -0,0 +1,374 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use rayon::{
+    ThreadPool, ThreadPoolBuilder,
+    iter::{IntoParallelRefIterator, ParallelIterator},
+};
+
+use crate::{
+    counts::CountTable,
+    data::{DEFAULT_NGRAM_SIZES, TieBreak, WordFilter, WordProcessor},
+    error::{Error, Result},
+    input::Source,
+    language::{DETECTION_SAMPLE, Language},
+    normalize::Normalizer,
+    stream::{Chunker, StreamAnalyzer},
+    tokenizer::{SimpleTokenizer, Tokenizer},
+};
+
+/// Builder for a text analysis
+///
+/// ```
+/// use wordfreq::{Analyzer, WordFilter};
+///
+/// let processor = Analyzer::new()
+///     .filter(WordFilter::english())
+///     .analyze("the cat sat on the mat with the other cat")
+///     .unwrap();
+/// assert_eq!(processor.words[0].text, "cat");
+/// ```
+pub struct Analyzer {
+    filter: Option<WordFilter>,
+    tie_break: TieBreak,
+    ngram_sizes: Vec<usize>,
+    tokenizer: Box<dyn Tokenizer>,
+    normalizer: Option<Box<dyn Normalizer>>,
+    detect_language: bool,
+    jobs: usize,
+    tracked: Vec<String>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self {
+            filter: None,
+            tie_break: TieBreak::default(),
+            ngram_sizes: DEFAULT_NGRAM_SIZES.to_vec(),
+            tokenizer: Box::new(SimpleTokenizer),
+            normalizer: None,
+            detect_language: false,
+            jobs: 1,
+            tracked: Vec::new(),
+        }
+    }
+}
+
+impl Analyzer {
+    /// An analyzer with no stopword filter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the stopword filter, `None` includes stopwords in the analysis
+    pub fn filter(mut self, filter: impl Into<Option<WordFilter>>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+
+    /// Set how words and n-grams with equal counts are ordered
+    pub fn tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Set which n-gram tables are computed, sizes below 2 are ignored
+    pub fn ngram_sizes(mut self, sizes: impl IntoIterator<Item = usize>) -> Self {
+        self.ngram_sizes = sizes.into_iter().collect();
+        self.ngram_sizes.sort_unstable();
+        self.ngram_sizes.dedup();
+        self
+    }
+
+    /// Set the tokenizer used to split the text into words
+    pub fn tokenizer(mut self, tokenizer: Box<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Detect the language of every document and use its built-in stopword list
+    /// instead of the filter, which is kept for undetected languages
+    pub fn detect_language(mut self, detect: bool) -> Self {
+        self.detect_language = detect;
+        self
+    }
+
+    /// Set the normalizer (stemmer or lemma dictionary) applied before counting,
+    /// `None` counts every form separately
+    pub fn normalizer(mut self, normalizer: Option<Box<dyn Normalizer>>) -> Self {
+        self.normalizer = normalizer;
+        self
+    }
+
+    /// Set how many threads analyze several sources, 0 uses every core
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Record the positions of these words, see [`WordProcessor::positions`]
+    pub fn track_positions<S: Into<String>>(mut self, words: impl IntoIterator<Item = S>) -> Self {
+        self.tracked = words.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Split `text` with the configured tokenizer
+    pub fn tokens<'t>(&self, text: &'t str) -> Vec<(usize, &'t str)> {
+        self.tokenizer.tokenize(text)
+    }
+
+    // whether streamed text can be split at any whitespace, or only at line breaks
+    pub(crate) fn splits_at_whitespace(&self) -> bool {
+        self.tokenizer.splits_at_whitespace()
+    }
+
+    /// An empty count table for this analyzer's n-gram sizes
+    pub fn count_table(&self) -> CountTable {
+        let tracked = self.tracked.iter().map(|word| self.normalize(word));
+        CountTable::new(&self.ngram_sizes).track(tracked)
+    }
+
+    /// The form `word` is counted under: lowercase, then normalized
+    pub fn normalize(&self, word: &str) -> String {
+        let word = word.to_lowercase();
+        match &self.normalizer {
+            Some(normalizer) => normalizer.normalize(&word).into_owned(),
+            None => word,
+        }
+    }
+
+    /// Tokenize `text` and add its words to `counts`
+    ///
+    /// With language detection on, `text` is treated as a whole document and its language detected
+    pub fn count_into(&self, text: &str, counts: &mut CountTable) {
+        let language = self.detect(text, counts);
+        self.count_with(text, counts, self.filter_for(language));
+    }
+
+    pub(crate) fn detects_language(&self) -> bool {
+        self.detect_language
+    }
+
+    // detect the language of a document (if enabled) and record it
+    pub(crate) fn detect(&self, text: &str, counts: &mut CountTable) -> Option<Language> {
+        if !self.detect_language {
+            return None;
+        }
+        let language = detect_sample(text);
+        counts.language = language;
+        language
+    }
+
+    /// The words of `text` as they are counted: lowercase, normalized and without stopwords
+    pub fn counted_words(&self, text: &str) -> Vec<String> {
+        self.counted_tokens(text)
+            .into_iter()
+            .filter_map(|(_, _, counted)| counted)
+            .collect()
+    }
+
+    /// Every token of `text` with the form it is counted under, `None` for stopwords
+    pub fn counted_tokens<'t>(&self, text: &'t str) -> Vec<(usize, &'t str, Option<String>)> {
+        let filter = self.filter_for(self.detect_language.then(|| detect_sample(text)).flatten());
+        self.tokens(text)
+            .into_iter()
+            .map(|(start, word)| {
+                let lowercase = word.to_lowercase();
+                let stopword = filter.is_some_and(|filter| filter.contains(&lowercase));
+                (start, word, (!stopword).then(|| self.normalize(&lowercase)))
+            })
+            .collect()
+    }
+
+    // the stopwords of a document in `language`
+    pub(crate) fn filter_for(&self, language: Option<Language>) -> Option<&WordFilter> {
+        match language {
+            Some(language) if self.detect_language => Some(language.stopwords()),
+            _ => self.filter.as_ref(),
+        }
+    }
+
+    pub(crate) fn count_with(
+        &self,
+        text: &str,
+        counts: &mut CountTable,
+        filter: Option<&WordFilter>,
+    ) {
+        let mut end = 0;
+        for (start, word) in self.tokens(text) {
+            counts.push_gap(&text[end..start], Some(word));
+            end = start + word.len();
+            let word = word.to_lowercase();
+            let stopword = filter.is_some_and(|filter| filter.contains(&word));
+            match &self.normalizer {
+                Some(normalizer) if !stopword => {
+                    let normalized = normalizer.normalize(&word).into_owned();
+                    counts.push_form(normalized, &word, stopword);
+                }
+                _ => counts.push(word, stopword),
+            }
+        }
+        counts.push_gap(&text[end..], None);
+    }
+
+    /// Build the statistics out of the collected counts
+    ///
+    /// # Errors
+    /// [`Error::EmptyInput`] if no words were counted
+    pub fn finish(&self, counts: CountTable) -> Result<WordProcessor> {
+        WordProcessor::from_counts(counts, self.tie_break)
+    }
+
+    /// Stream everything from `reader` into `counts` as one document
+    ///
+    /// # Errors
+    /// [`Error::Io`] if reading fails or the text isn't valid UTF-8
+    pub fn count_reader(&self, reader: impl BufRead, counts: &mut CountTable) -> Result<()> {
+        let mut chunker = Chunker::default();
+        chunker.read_from(self, counts, reader)?;
+        chunker.flush(self, counts)?;
+        counts.end_document();
+        Ok(())
+    }
+
+    /// Start an incremental analysis
+    pub fn stream(&self) -> StreamAnalyzer<'_> {
+        StreamAnalyzer::new(self)
+    }
+
+    /// Analyze a text
+    ///
+    /// # Errors
+    /// [`Error::EmptyInput`] if no words are left after filtering
+    pub fn analyze(&self, text: &str) -> Result<WordProcessor> {
+        let mut counts = self.count_table();
+        self.count_into(text, &mut counts);
+        self.finish(counts)
+    }
+
+    /// Analyze everything from `reader` chunk by chunk, without holding the whole text in memory
+    ///
+    /// # Errors
+    /// [`Error::Io`] if reading fails or the text isn't valid UTF-8,
+    /// otherwise the same as [`Analyzer::analyze`]
+    pub fn analyze_buf_read(&self, reader: impl BufRead) -> Result<WordProcessor> {
+        let mut stream = self.stream();
+        stream.read_from(reader)?;
+        stream.finish()
+    }
+
+    /// Analyze everything from `reader`, see [`Analyzer::analyze_buf_read`]
+    ///
+    /// # Errors
+    /// The same as [`Analyzer::analyze_buf_read`]
+    pub fn analyze_reader(&self, reader: impl Read) -> Result<WordProcessor> {
+        self.analyze_buf_read(BufReader::new(reader))
+    }
+
+    /// Count every source into its own table, in parallel
+    ///
+    /// # Errors
+    /// [`Error::Io`] if a source can't be opened or read
+    pub fn count_sources(&self, sources: &[Source]) -> Result<Vec<CountTable>> {
+        self.pool()?.install(|| {
+            sources
+                .par_iter()
+                .map(|source| self.count_source(source))
+                .collect()
+        })
+    }
+
+    /// Analyze several sources as one corpus, in parallel
+    ///
+    /// The result is the same as analyzing the sources one after the other
+    ///
+    /// # Errors
+    /// [`Error::Io`] if a source can't be opened or read, otherwise the same as [`Analyzer::analyze`]
+    pub fn analyze_sources(&self, sources: &[Source]) -> Result<WordProcessor> {
+        let counts = self.pool()?.install(|| {
+            // reduce keeps the order of the sources, so first occurrences match a serial run
+            sources
+                .par_iter()
+                .map(|source| self.count_source(source))
+                .try_reduce(
+                    || self.count_table(),
+                    |mut a, b| {
+                        a.merge(b);
+                        Ok(a)
+                    },
+                )
+        })?;
+        self.finish(counts)
+    }
+
+    fn count_source(&self, source: &Source) -> Result<CountTable> {
+        let mut counts = self.count_table();
+        self.count_reader(source.open()?, &mut counts)
+            .map_err(|e| match e {
+                Error::Io(e) => Error::Io(source.failed("read", e)),
+                e => e,
+            })?;
+        Ok(counts)
+    }
+
+    fn pool(&self) -> Result<ThreadPool> {
+        ThreadPoolBuilder::new()
+            .num_threads(self.jobs)
+            .build()
+            .map_err(|e| Error::Io(std::io::Error::other(e)))
+    }
+
+    /// Analyze the file at `path`
+    ///
+    /// # Errors
+    /// [`Error::Io`] if the file can't be opened or read, otherwise the same as [`Analyzer::analyze`]
+    pub fn analyze_path(&self, path: impl AsRef<Path>) -> Result<WordProcessor> {
+        self.analyze_reader(File::open(path)?)
+    }
+}
+
+// detect the language from the start of the text
+fn detect_sample(text: &str) -> Option<Language> {
+    let mut end = text.len().min(DETECTION_SAMPLE);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Language::detect(&text[..end])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Analyzer, Source, TieBreak, WordFilter};
+
+    #[test]
+    fn parallel_matches_serial() {
+        let sources = ["metamorphosis.txt", "the_stranger.txt", "metamorphosis.txt"]
+            .map(|path| Source::Path(path.into()));
+        let analyze = |jobs| {
+            let processor = Analyzer::new()
+                .filter(WordFilter::english())
+                .tie_break(TieBreak::FirstOccurrence)
+                .jobs(jobs)
+                .analyze_sources(&sources)
+                .unwrap();
+            serde_json::to_value(processor).unwrap()
+        };
+        let serial = analyze(1);
+        assert_eq!(serial, analyze(4));
+
+        // and the same as one big text with document breaks
+        let analyzer = Analyzer::new()
+            .filter(WordFilter::english())
+            .tie_break(TieBreak::FirstOccurrence);
+        let mut counts = analyzer.count_table();
+        for source in &sources {
+            analyzer.count_into(&source.read_to_string().unwrap(), &mut counts);
+            counts.end_document();
+        }
+        let whole = analyzer.finish(counts).unwrap();
+        assert_eq!(serial, serde_json::to_value(whole).unwrap());
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{data::WordProcessor, keyness::log_likelihood};
+
+/// Statistic the n-grams are ranked by
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum AssociationMeasure {
+    /// Raw count
+    #[default]
+    Count,
+    /// Pointwise mutual information, favors rare exclusive pairs
+    Pmi,
+    /// PMI scaled to [-1, 1]
+    Npmi,
+    /// Favors frequent pairs
+    TScore,
+    /// Simple log-likelihood against independence
+    LogLikelihood,
+    /// Dice coefficient
+    Dice,
+}
+
+/// Association scores of one n-gram
+///
+/// The expected count assumes the words occur independently: `N * p(w1) * ... * p(wn)`,
+/// with `N` the number of counted words
+#[derive(Debug, Serialize)]
+pub struct Collocation {
+    pub text: String,
+    pub count: usize,
+    /// log2(observed / expected)
+    pub pmi: f64,
+    /// PMI divided by -log2(p(n-gram))
+    pub npmi: f64,
+    /// (observed - expected) / sqrt(observed)
+    pub t_score: f64,
+    /// 2 * (observed * ln(observed / expected) - (observed - expected))
+    pub log_likelihood: f64,
+    /// n * observed / sum of the word counts
+    pub dice: f64,
+}
+
+impl Collocation {
+    pub fn score(&self, measure: AssociationMeasure) -> f64 {
+        match measure {
+            AssociationMeasure::Count => self.count as f64,
+            AssociationMeasure::Pmi => self.pmi,
+            AssociationMeasure::Npmi => self.npmi,
+            AssociationMeasure::TScore => self.t_score,
+            AssociationMeasure::LogLikelihood => self.log_likelihood,
+            AssociationMeasure::Dice => self.dice,
+        }
+    }
+}
+
+/// Score the `n`-grams seen at least `min_count` times, best first by `measure`
+pub fn collocations(
+    processor: &WordProcessor,
+    n: usize,
+    min_count: usize,
+    measure: AssociationMeasure,
+) -> Vec<Collocation> {
+    let words: HashMap<&str, usize> = processor
+        .words
+        .iter()
+        .map(|word| (word.text.as_str(), word.count))
+        .collect();
+    let total = processor.total_words as f64;
+
+    let mut collocations = processor
+        .ngrams(n)
+        .iter()
+        .filter(|gram| gram.count >= min_count)
+        .map(|gram| {
+            // n-grams never contain stopwords, so every word is counted
+            let counts = gram
+                .text
+                .split(' ')
+                .map(|word| words.get(word).copied().unwrap_or(1) as f64)
+                .collect::<Vec<_>>();
+            let observed = gram.count as f64;
+            let expected = total * counts.iter().map(|count| count / total).product::<f64>();
+            let pmi = (observed / expected).log2();
+            Collocation {
+                text: gram.text.clone(),
+                count: gram.count,
+                pmi,
+                npmi: match -(observed / total).log2() {
+                    0.0 => 1.0,
+                    h => pmi / h,
+                },
+                t_score: (observed - expected) / observed.sqrt(),
+                log_likelihood: 2.0
+                    * (observed * (observed / expected).ln() - (observed - expected)),
+                dice: counts.len() as f64 * observed / counts.iter().sum::<f64>(),
+            }
+        })
+        .collect::<Vec<_>>();
+    // stable sort, ties keep the frequency order
+    collocations.sort_by(|a, b| b.score(measure).total_cmp(&a.score(measure)));
+    collocations
+}
+
+/// A word found near a node word, with its association scores
+///
+/// The expected count is `f(node) * f(word) * span / N`, with `span` the words
+/// looked at around every occurrence of the node and `N` the number of counted words
+#[derive(Debug, Serialize)]
+pub struct Collocate {
+    pub text: String,
+    /// Occurrences within the span of the node
+    pub count: usize,
+    /// Occurrences left of the node
+    pub left: usize,
+    /// Occurrences right of the node
+    pub right: usize,
+    /// Occurrences in the whole text
+    pub frequency: usize,
+    /// Mutual information, log2(observed / expected)
+    pub mi: f64,
+    /// MI divided by -log2(observed / N)
+    pub npmi: f64,
+    pub t_score: f64,
+    /// Log-likelihood (G²) of the frequency inside the spans vs outside of them
+    pub log_likelihood: f64,
+    /// 2 * observed / (f(node) + f(word))
+    pub dice: f64,
+}
+
+impl Collocate {
+    pub fn score(&self, measure: AssociationMeasure) -> f64 {
+        match measure {
+            AssociationMeasure::Count => self.count as f64,
+            AssociationMeasure::Pmi => self.mi,
+            AssociationMeasure::Npmi => self.npmi,
+            AssociationMeasure::TScore => self.t_score,
+            AssociationMeasure::LogLikelihood => self.log_likelihood,
+            AssociationMeasure::Dice => self.dice,
+        }
+    }
+}
+
+/// Words seen at most `left` words before or `right` words after `node`, best first by `measure`
+///
+/// A word in the spans of two occurrences of the node is counted once, on the side of the first
+/// `documents` hold the counted words of every text, see [`crate::Analyzer::counted_words`],
+/// so the spans skip stopwords and never cross two texts. The frequencies come from `processor`
+pub fn collocates<'d>(
+    documents: impl IntoIterator<Item = &'d [String]>,
+    node: &str,
+    (left, right): (usize, usize),
+    processor: &WordProcessor,
+    min_count: usize,
+    measure: AssociationMeasure,
+) -> Vec<Collocate> {
+    // word -> (left, right) occurrences
+    let mut found: HashMap<&str, (usize, usize)> = HashMap::new();
+    let mut span_words = 0;
+    for words in documents {
+        // overlapping spans count every word once
+        let mut seen = vec![false; words.len()];
+        for (i, _) in words.iter().enumerate().filter(|(_, word)| *word == node) {
+            let before = i.saturating_sub(left)..i;
+            let after = i + 1..(i + 1 + right).min(words.len());
+            for (j, is_left) in before.map(|j| (j, true)).chain(after.map(|j| (j, false))) {
+                if std::mem::replace(&mut seen[j], true) {
+                    continue;
+                }
+                span_words += 1;
+                let entry = found.entry(&words[j]).or_default();
+                if is_left {
+                    entry.0 += 1;
+                } else {
+                    entry.1 += 1;
+                }
+            }
+        }
+    }
+
+    let frequencies: HashMap<&str, usize> = processor
+        .words
+        .iter()
+        .map(|word| (word.text.as_str(), word.count))
+        .collect();
+    let total = processor.total_words as f64;
+    let node_frequency = frequencies.get(node).copied().unwrap_or(0) as f64;
+    let span = (left + right) as f64;
+
+    let mut collocates = found
+        .into_iter()
+        .filter(|(text, (l, r))| *text != node && l + r >= min_count)
+        .map(|(text, (l, r))| {
+            let frequency = frequencies.get(text).copied().unwrap_or(l + r);
+            let observed = (l + r) as f64;
+            let expected = node_frequency * frequency as f64 * span / total;
+            let mi = (observed / expected).log2();
+            Collocate {
+                text: text.to_string(),
+                count: l + r,
+                left: l,
+                right: r,
+                frequency,
+                mi,
+                npmi: match -(observed / total).log2() {
+                    0.0 => 1.0,
+                    h => mi / h,
+                },
+                t_score: (observed - expected) / observed.sqrt(),
+                log_likelihood: log_likelihood(
+                    observed,
+                    frequency as f64 - observed,
+                    span_words as f64,
+                    total - span_words as f64,
+                ),
+                dice: 2.0 * observed / (node_frequency + frequency as f64),
+            }
+        })
+        .collect::<Vec<_>>();
+    // ties are alphabetical
+    collocates.sort_by(|a, b| a.text.cmp(&b.text));
+    collocates.sort_by(|a, b| b.score(measure).total_cmp(&a.score(measure)));
+    collocates
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Analyzer;
+
+    #[test]
+    fn pairs_and_collocates() {
+        let processor = Analyzer::new()
+            .ngram_sizes([2])
+            .analyze("red wine red car red car blue car new york")
+            .unwrap();
+
+        let pmi = collocations(&processor, 2, 1, AssociationMeasure::Pmi);
+        assert_eq!(pmi[0].text, "new york");
+        // 1 in 10 words, each word seen once
+        assert!((pmi[0].pmi - 10f64.log2()).abs() < 1e-9);
+        assert_eq!(pmi[0].dice, 1.0);
+
+        let frequent = collocations(&processor, 2, 2, AssociationMeasure::TScore);
+        assert_eq!(frequent.len(), 1);
+        assert_eq!(frequent[0].text, "red car");
+
+        let words = Analyzer::new().counted_words("red wine red car red car blue car new york");
+        let near_car = collocates(
+            [words.as_slice()],
+            "car",
+            (1, 0),
+            &processor,
+            1,
+            AssociationMeasure::Count,
+        );
+        assert_eq!(near_car[0].text, "red");
+        assert_eq!((near_car[0].count, near_car[0].left), (2, 2));
+        assert_eq!(near_car[1].text, "blue");
+    }
+}
@@ -1,381 +1,1281 @@
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
 use rand::Rng;
 use std::{
     fs::{File, OpenOptions},
-    io::{Read, Write},
-    path::PathBuf,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
 };
 
-use clap::{Args, Parser};
+use clap::{
+    Args, CommandFactory, Parser, Subcommand, ValueEnum,
+    builder::{ArgPredicate, PossibleValue, PossibleValuesParser, TypedValueParser},
+};
+use serde::Serialize;
 
-use crate::data::{WordData, WordFilter, WordProcessor};
+use wordfreq::{
+    Analyzer, Comparison, Concordance, Index, Keyness, Language, LemmaDictionary, Metadata,
+    Normalizer, Source, SourceWalker, Stemmer, TieBreak, Tokenizer, TokenizerKind, WordData,
+    WordFilter, WordProcessor,
+    collocation::{AssociationMeasure, collocates, collocations},
+    concordance::{Hit, MatchKind, SortBy, sort_hits},
+    data::DEFAULT_NGRAM_SIZES,
+    dispersion::{Dispersion, barcode},
+    format::{OutputFormat, Tabular},
+    keyness::{KeynessMeasure, Significance},
+    laws::{Laws, rank_frequency},
+    schema::Document,
+    tfidf::{IdfWeight, TfIdf, TfWeight},
+};
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub struct Commands {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Analyze the texts as one corpus: statistics, top words, keywords...
+    Analyze(Analyze),
+    /// Compare the texts, each analyzed on its own
+    Compare(Compare),
+    /// List every occurrence of TEXT with its context
+    Concordance(ConcordanceCommand),
+    /// List the words found near WORD
+    Collocates(Collocates),
+    /// List the top n-grams of any size
+    Ngrams(Ngrams),
+    /// Print a word cloud of the top words
+    Cloud(Cloud),
+    /// Index the texts to query them without reading them again
+    Index(IndexCommand),
+    /// Query an index made by the index subcommand
+    Query(QueryCommand),
+    /// Print the JSON Schema of a document written by --out
+    Schema {
+        #[arg(value_enum, default_value_t = Document::Analysis)]
+        document: Document,
+    },
+    /// Print the completion script of a shell
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Write the man pages of every subcommand into DIR
+    Man {
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+    },
+}
+
+/// The texts to read
+#[derive(Args)]
+pub struct Inputs {
+    /// Files, directories or - for stdin, all read as one corpus
+    #[arg(value_name = "PATH", required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Walk directories recursively
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Only read files in directories matching the glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files in directories matching the glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+}
+
+/// How the text is split into words
+#[derive(Args)]
+pub struct TokenizerArgs {
+    /// How to split the text into words
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = TokenizerKind::Simple,
+        default_value_if("token_pattern", ArgPredicate::IsPresent, "regex")
+    )]
+    tokenizer: TokenizerKind,
+
+    /// Regular expression matching one word, implies the regex tokenizer
+    #[arg(long, value_name = "REGEX")]
+    token_pattern: Option<String>,
+}
+
+/// How the words are counted
+#[derive(Args)]
+pub struct AnalysisArgs {
     #[command(flatten)]
-    file_args: FileArgs,
+    tokenizer: TokenizerArgs,
 
     /// Whether to include stopwords
     #[arg(short = 'a', long)]
-    pub analyze_stopwords: bool,
+    analyze_stopwords: bool,
+
+    /// Language of the stopword list and stemmer, auto detects the stopword list of every text
+    #[arg(long, value_name = "LANG", default_value = "en", value_parser = lang_parser())]
+    lang: Lang,
+
+    /// Count words by their stem, so "runs" and "running" count as "run"
+    #[arg(long)]
+    stem: bool,
+
+    /// Count words by their lemma, from a JSON object mapping forms to lemmas
+    #[arg(long, value_name = "PATH", conflicts_with = "stem")]
+    lemmas: Option<PathBuf>,
+
+    /// Custom stopword filter to use instead of the default one
+    #[arg(long)]
+    custom_filter: Option<PathBuf>,
+
+    /// How to order words with the same count
+    #[arg(long, value_enum, default_value_t = TieBreak::Alphabetical)]
+    tie_break: TieBreak,
+
+    /// Number of files analyzed in parallel, 0 uses every core
+    #[arg(short, long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
+}
+
+/// Where the results are written
+#[derive(Args)]
+pub struct OutputArgs {
+    /// Path to write to
+    #[arg(long, short = 'o')]
+    out: Option<PathBuf>,
+
+    /// Format of the --out file
+    #[arg(long, value_enum, default_value_t = OutputFormat::Json, requires = "out")]
+    format: OutputFormat,
+}
+
+/// How concordance hits are matched and shown
+#[derive(Args)]
+pub struct ContextArgs {
+    /// How TEXT matches words
+    #[arg(long = "match", value_enum, default_value_t = MatchKind::Exact)]
+    match_kind: MatchKind,
+
+    /// Words of context left of every hit
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    left: usize,
+
+    /// Words of context right of every hit
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    right: usize,
+
+    /// Order of the hits
+    #[arg(long, value_enum, default_value_t = SortBy::Position)]
+    sort: SortBy,
+
+    /// Maximum hits to be listed
+    #[arg(long)]
+    max: Option<usize>,
+}
+
+/// How n-grams and collocates are ranked
+#[derive(Args)]
+pub struct RankArgs {
+    /// List the top N
+    #[arg(short = 't', long, value_name = "N", default_value_t = 10)]
+    top: usize,
+
+    /// Association measure to rank by
+    #[arg(long, value_enum, default_value_t = AssociationMeasure::Count)]
+    rank_by: AssociationMeasure,
+
+    /// Skip those seen fewer times, the association measures overrate rare ones
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    min_count: usize,
+}
+
+#[derive(Args)]
+pub struct Analyze {
+    #[command(flatten)]
+    inputs: Inputs,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
 
     /// List the top N words
-    #[arg(short = 't', long, value_name = "N", value_parser = 1..10000, requires = "analyze")]
-    pub top: Option<i64>,
+    #[arg(short = 't', long, value_name = "N", value_parser = 1..10000)]
+    top: Option<i64>,
 
     /// Show various statistics about diversity
     #[arg(short = 'd', long)]
-    pub diversity: bool,
+    diversity: bool,
 
-    /// Path to write to
-    #[arg(long, short = 'o', requires = "analyze")]
-    pub out: Option<PathBuf>,
+    /// Also keep the n-grams of these sizes in the --out analysis, besides bigrams and trigrams
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u16).range(2..),
+        value_delimiter = ',',
+        requires = "out"
+    )]
+    n_grams: Vec<u16>,
+
+    /// Also print statistics for every input file
+    #[arg(long)]
+    per_file: bool,
 
-    /// Whether to print n_gram data
-    #[arg(long, value_parser = 2..4, requires = "top", requires = "analyze")]
-    pub n_grams: Option<i64>,
+    /// Reference corpus to find the keywords of the analyzed texts against
+    #[arg(long, value_name = "PATH", num_args = 1..)]
+    keyness: Vec<PathBuf>,
 
-    /// Custom stopword filter to use instead of the default one
+    /// Statistic to rank the keywords by
+    #[arg(long, value_enum, default_value_t = KeynessMeasure::LogLikelihood, requires = "keyness")]
+    keyness_by: KeynessMeasure,
+
+    /// Significance level (p) of the log-likelihood test a keyword must pass
+    #[arg(long, value_enum, default_value_t = Significance::P01, requires = "keyness")]
+    significance: Significance,
+
+    /// Rank the terms of every input file by TF-IDF over the collection (--out also writes this ranking, to out-tfidf)
     #[arg(long)]
-    pub custom_filter: Option<PathBuf>,
+    tfidf: bool,
 
-    /// Context search string
-    #[arg(long, requires = "analyze")]
-    pub concordance: Option<String>,
+    /// Term frequency weighting for --tfidf
+    #[arg(long, value_enum, default_value_t = TfWeight::Log, requires = "tfidf")]
+    tf: TfWeight,
+
+    /// Inverse document frequency weighting for --tfidf
+    #[arg(long, value_enum, default_value_t = IdfWeight::Smooth, requires = "tfidf")]
+    idf: IdfWeight,
 
-    /// Maximum examples to be included
-    #[arg(long, requires = "concordance", requires = "analyze")]
-    pub max: Option<usize>,
+    /// Print the readability scores (Flesch, Flesch-Kincaid, Gunning fog, SMOG, Coleman-Liau)
+    #[arg(long)]
+    readability: bool,
 
-    /// Whether to print a word cloud
-    #[arg(long, requires = "analyze")]
-    pub cloud: bool,
+    /// Print sentence and paragraph statistics
+    #[arg(long)]
+    structure: bool,
 
-    /// Custom word cloud width
+    /// Show where in the text the given words occur, and how evenly
+    #[arg(long, value_name = "WORD", value_delimiter = ',')]
+    dispersion: Vec<String>,
+
+    /// Equal segments the text is cut into for --dispersion
     #[arg(
         long,
-        short,
-        requires = "cloud",
-        default_value_t = 40,
-        requires = "analyze"
+        value_name = "N",
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u16).range(2..),
+        requires = "dispersion"
     )]
-    pub width: usize,
+    segments: u16,
+
+    /// Fit Zipf's law (rank vs frequency) and Heaps' law (vocabulary growth)
+    #[arg(long)]
+    laws: bool,
+
+    /// Write the rank/frequency points as CSV, for plotting
+    #[arg(long, value_name = "PATH")]
+    zipf_data: Option<PathBuf>,
+
+    /// Write the vocabulary growth curve as CSV, for plotting
+    #[arg(long, value_name = "PATH")]
+    heaps_data: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct Compare {
+    /// The texts, a .json path being an analysis saved with analyze --out
+    /// and a directory giving one text per file directly inside it
+    #[arg(value_name = "PATH", num_args = 2.., required = true)]
+    paths: Vec<PathBuf>,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    /// Number of words listed for the vocabulary and frequency differences
+    #[arg(short = 't', long, value_name = "N", default_value_t = 10)]
+    top: usize,
+
+    /// Show various statistics about the diversity of every text
+    #[arg(short = 'd', long)]
+    diversity: bool,
 }
 
 #[derive(Args)]
-#[group(required = true, multiple = false)]
-pub struct FileArgs {
-    /// Analyze one text
-    #[arg(value_name = "PATH")]
-    analyze: Option<PathBuf>,
+pub struct ConcordanceCommand {
+    /// Context search string
+    #[arg(value_name = "TEXT")]
+    query: String,
 
-    /// Compare the texts
-    #[clap(long,value_name = "PATHS", value_delimiter = ' ', num_args = 2..3)]
-    compare: Option<Vec<PathBuf>>,
+    #[command(flatten)]
+    inputs: Inputs,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+
+    #[command(flatten)]
+    context: ContextArgs,
+
+    /// Write the hits, with their byte offsets, as JSON
+    #[arg(long, short = 'o', value_name = "PATH")]
+    out: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct Collocates {
+    /// The word whose neighbours are listed
+    #[arg(value_name = "WORD")]
+    word: String,
+
+    #[command(flatten)]
+    inputs: Inputs,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    #[command(flatten)]
+    rank: RankArgs,
+
+    /// Counted words looked at before every occurrence of WORD
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    span_left: usize,
+
+    /// Counted words looked at after every occurrence of WORD
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    span_right: usize,
+}
+
+#[derive(Args)]
+pub struct Ngrams {
+    #[command(flatten)]
+    inputs: Inputs,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    #[command(flatten)]
+    rank: RankArgs,
+
+    /// Sizes of the n-grams, n-grams containing a stopword are skipped
+    #[arg(
+        short = 'n',
+        long = "size",
+        value_name = "N",
+        default_value = "2",
+        value_parser = clap::value_parser!(u16).range(2..),
+        value_delimiter = ','
+    )]
+    sizes: Vec<u16>,
+}
+
+#[derive(Args)]
+pub struct Cloud {
+    #[command(flatten)]
+    inputs: Inputs,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    /// Custom word cloud width
+    #[arg(long, short, default_value_t = 40)]
+    width: usize,
+}
+
+#[derive(Args)]
+pub struct IndexCommand {
+    #[command(flatten)]
+    inputs: Inputs,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    /// Path of the index file
+    #[arg(long, short = 'o', value_name = "INDEX")]
+    out: PathBuf,
+}
+
+#[derive(Args)]
+pub struct QueryCommand {
+    /// Path of the index file
+    #[arg(value_name = "INDEX")]
+    index: PathBuf,
+
+    #[command(subcommand)]
+    query: IndexQuery,
+}
+
+#[derive(Subcommand)]
+pub enum IndexQuery {
+    /// List the top N words, or n-grams
+    Top {
+        #[arg(short = 't', long, value_name = "N", default_value_t = 10)]
+        top: usize,
+
+        /// Size of the n-grams to list instead of words
+        #[arg(long, value_name = "N")]
+        n_grams: Option<usize>,
+    },
+    /// List every occurrence of TEXT with its context
+    Concordance {
+        #[arg(value_name = "TEXT")]
+        query: String,
+
+        #[command(flatten)]
+        context: ContextArgs,
+    },
+    /// Show where in the corpus the words occur, and how evenly
+    Dispersion {
+        #[arg(value_name = "WORD", value_delimiter = ',', required = true)]
+        words: Vec<String>,
+
+        /// Equal segments the corpus is cut into
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 10,
+            value_parser = clap::value_parser!(u16).range(2..)
+        )]
+        segments: u16,
+    },
+}
+
+// none for auto, an alias so clap doesn't make --lang optional
+type Lang = Option<Language>;
+
+// auto or one of the languages
+fn lang_parser() -> impl TypedValueParser<Value = Lang> {
+    let languages = Language::value_variants()
+        .iter()
+        .filter_map(ValueEnum::to_possible_value);
+    PossibleValuesParser::new(std::iter::once(PossibleValue::new("auto")).chain(languages))
+        .map(|name| Language::from_str(&name, false).ok())
+}
+
+// columns of the --dispersion plot
+const BARCODE_WIDTH: usize = 60;
+
+// the concordance hits of one input, for concordance --out
+#[derive(Serialize)]
+struct SourceHits {
+    source: String,
+    hits: Vec<Hit>,
 }
 
 impl Commands {
-    pub fn top(&self, processor: &WordProcessor) {
-        let Some(num) = self.top.map(|num| num as usize) else {
-            return;
+    pub fn handle_commands(&self) -> Result<()> {
+        match &self.command {
+            Command::Analyze(analyze) => analyze.run(),
+            Command::Compare(compare) => compare.run(),
+            Command::Concordance(concordance) => concordance.run(),
+            Command::Collocates(collocates) => collocates.run(),
+            Command::Ngrams(ngrams) => ngrams.run(),
+            Command::Cloud(cloud) => cloud.run(),
+            Command::Index(index) => index.run(),
+            Command::Query(query) => query.run(),
+            Command::Schema { document } => {
+                println!("{:#}", document.schema());
+                Ok(())
+            }
+            Command::Completions { shell } => {
+                clap_complete::generate(
+                    *shell,
+                    &mut Self::command(),
+                    "wordfreq",
+                    &mut io::stdout(),
+                );
+                Ok(())
+            }
+            Command::Man { dir } => std::fs::create_dir_all(dir)
+                .and_then(|()| clap_mangen::generate_to(Self::command(), dir))
+                .context("could not write the man pages"),
+        }
+    }
+
+    /// Whether the command analyzes texts, the others print files meant to be redirected
+    pub fn timed(&self) -> bool {
+        !matches!(
+            self.command,
+            Command::Schema { .. } | Command::Completions { .. } | Command::Man { .. }
+        )
+    }
+}
+
+impl Inputs {
+    // expand the paths
+    fn sources(&self) -> Result<Vec<Source>> {
+        self.walker()
+            .and_then(|walker| walker.collect(&self.paths))
+            .context("could not collect the input files")
+    }
+
+    fn walker(&self) -> wordfreq::Result<SourceWalker> {
+        SourceWalker::new()
+            .recursive(self.recursive)
+            .include(&self.include)?
+            .exclude(&self.exclude)
+    }
+
+    // the saved analysis, if that's what the paths are
+    fn saved(&self) -> Result<Option<&Path>> {
+        saved(&self.paths)
+    }
+
+    // the saved analysis, or the analysis of the texts
+    fn analyze(&self, analysis: &AnalysisArgs, analyzer: &Analyzer) -> Result<WordProcessor> {
+        match self.saved()? {
+            Some(path) => analysis.load(path),
+            None => Ok(analyzer.analyze_sources(&self.sources()?)?),
+        }
+    }
+
+    // every text with its name
+    fn read(&self) -> Result<Vec<(String, String)>> {
+        if self.saved()?.is_some() {
+            return Err(anyhow!("the texts are needed, a saved analysis has none"));
+        }
+        self.sources()?
+            .iter()
+            .map(|source| {
+                let text = source
+                    .read_to_string()
+                    .with_context(|| format!("could not read {source}"))?;
+                Ok((source.to_string(), text))
+            })
+            .collect()
+    }
+}
+
+impl TokenizerArgs {
+    fn build(&self) -> Result<Box<dyn Tokenizer>> {
+        self.tokenizer
+            .build(self.token_pattern.as_deref())
+            .context("could not build the tokenizer")
+    }
+}
+
+impl AnalysisArgs {
+    // the analyzer for these options
+    fn analyzer(&self) -> Result<Analyzer> {
+        let normalizer: Option<Box<dyn Normalizer>> = if let Some(path) = &self.lemmas {
+            let lemmas = LemmaDictionary::from_path(path).with_context(|| {
+                format!("could not load the lemma dictionary {}", path.display())
+            })?;
+            Some(Box::new(lemmas))
+        } else if self.stem {
+            // one stemmer serves every text, only the stopword lists follow the detection
+            let language = self
+                .lang
+                .context("--stem needs a language, --lang auto only picks the stopword list")?;
+            Some(Box::new(Stemmer::new(language.stemmer())))
+        } else {
+            None
         };
-        println!();
-        // turn the val to a usize
-        if let Some(n_grams) = self.n_grams.map(|n| n as usize) {
-            if n_grams == 2 {
-                if num > processor.bigrams.len() {
-                    println!("the given number exceeds the total word count. continuing anyway");
-                }
-                println!("\ntop {num} bigrams:");
-                for (i, WordData { text, count }) in processor.bigrams.iter().take(num).enumerate()
-                {
-                    let percent = 100.0 * *count as f64 / processor.unique_words as f64;
-                    println!(
-                        "    {}. {text:<10?} - {count} appearances ({percent:.2}%)",
-                        i + 1
-                    );
-                }
-            } else {
-                if num > processor.trigrams.len() {
-                    println!("the given number exceeds the total word count. continuing anyway");
-                }
-                println!("top {num} trigrams:");
-                for (i, WordData { text, count }) in processor.trigrams.iter().take(num).enumerate()
-                {
-                    let percent = 100.0 * *count as f64 / processor.unique_words as f64;
-                    println!(
-                        "    {}. {text:<10?} - {count} appearances ({percent:.2}%)",
-                        i + 1
-                    );
-                }
+        Ok(Analyzer::new()
+            .filter(self.get_word_filter()?)
+            .tie_break(self.tie_break)
+            .tokenizer(self.tokenizer.build()?)
+            .normalizer(normalizer)
+            .detect_language(
+                self.lang.is_none() && !self.analyze_stopwords && self.custom_filter.is_none(),
+            )
+            .jobs(self.jobs))
+    }
+
+    // get the word filter
+    fn get_word_filter(&self) -> Result<Option<WordFilter>> {
+        if self.analyze_stopwords {
+            return Ok(None);
+        }
+        if let Some(path) = self.custom_filter.as_ref() {
+            // possibly add fallback fuzzy plaintext search?
+            let filter = WordFilter::from_path(path).with_context(|| {
+                format!(
+                    "could not parse the words from the filter file {} (needs json)",
+                    path.display()
+                )
+            })?;
+            Ok(Some(filter))
+        } else {
+            // auto detection falls back to english
+            let language = self.lang.unwrap_or(Language::English);
+            Ok(Some(language.stopwords().clone()))
+        }
+    }
+
+    // load a saved analysis, warning about the options given now that it wasn't made with
+    fn load(&self, path: &Path) -> Result<WordProcessor> {
+        let processor = WordProcessor::from_path(path)
+            .with_context(|| format!("could not load the analysis {}", path.display()))?;
+        let Some(saved) = &processor.metadata else {
+            return Ok(processor);
+        };
+        let given = self.metadata(&[]);
+        let tokenizer = |kind: TokenizerKind| {
+            kind.to_possible_value()
+                .map(|value| value.get_name().to_string())
+        };
+        let options = [
+            (
+                "tokenizer",
+                tokenizer(saved.tokenizer),
+                tokenizer(given.tokenizer),
+            ),
+            (
+                "token pattern",
+                saved.token_pattern.clone(),
+                given.token_pattern,
+            ),
+            ("stopword filter", saved.filter.clone(), given.filter),
+            ("normalizer", saved.normalizer.clone(), given.normalizer),
+        ];
+        for (option, saved, given) in options {
+            if saved != given {
+                eprintln!(
+                    "warning! {} was made with the {option} {}, not {}, its counts are used as saved",
+                    path.display(),
+                    saved.as_deref().unwrap_or("none"),
+                    given.as_deref().unwrap_or("none")
+                );
             }
+        }
+        Ok(processor)
+    }
+
+    // how the analysis of `paths` is made, for the --out header and the index
+    fn metadata(&self, paths: &[PathBuf]) -> Metadata {
+        let filter = if self.analyze_stopwords {
+            None
+        } else if let Some(path) = &self.custom_filter {
+            Some(path.display().to_string())
         } else {
-            if num > processor.words.len() {
-                println!("the given number exceeds the total word count. continuing anyway");
+            Some(
+                self.lang
+                    .and_then(|language| language.to_possible_value())
+                    .map_or_else(|| "auto".to_string(), |value| value.get_name().to_string()),
+            )
+        };
+        let normalizer = match &self.lemmas {
+            Some(path) => Some(path.display().to_string()),
+            None => self.stem.then(|| "stem".to_string()),
+        };
+        let tokenizer = &self.tokenizer;
+        Metadata {
+            tokenizer: tokenizer.tokenizer,
+            token_pattern: tokenizer
+                .token_pattern
+                .clone()
+                .filter(|_| tokenizer.tokenizer == TokenizerKind::Regex),
+            filter,
+            normalizer,
+            ..Metadata::new(
+                paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            )
+        }
+    }
+}
+
+impl OutputArgs {
+    pub fn write(&self, value: &(impl Serialize + Tabular)) -> Result<()> {
+        match self.out.as_ref() {
+            Some(out) => self.write_to(out, value),
+            None => Ok(()),
+        }
+    }
+
+    // write next to the --out file, out.json -> out-{name}.json
+    pub fn write_beside(&self, name: &str, value: &(impl Serialize + Tabular)) -> Result<()> {
+        match self.out.as_ref() {
+            Some(out) => self.write_to(&sibling(out, name), value),
+            None => Ok(()),
+        }
+    }
+
+    fn write_to(&self, out: &Path, value: &(impl Serialize + Tabular)) -> Result<()> {
+        if let Some(formatter) = self.format.formatter() {
+            let tables = value.tables();
+            let files = if formatter.one_table_per_file() && tables.len() > 1 {
+                // out.csv -> out-words.csv, out-2-grams.csv...
+                tables
+                    .chunks(1)
+                    .map(|table| (sibling(out, &table[0].name), table))
+                    .collect()
+            } else {
+                vec![(out.to_path_buf(), tables.as_slice())]
+            };
+            for (path, tables) in files {
+                let mut data = Vec::new();
+                formatter
+                    .write(tables, &mut data)
+                    .context("could not format the tables")?;
+                write_to_file(&path, &String::from_utf8_lossy(&data))?;
+                println!("success. writing to {path:?}");
             }
-            println!("top {num} words:");
-            for (i, WordData { text, count }) in processor.words.iter().take(num).enumerate() {
-                let percent = 100.0 * *count as f64 / processor.unique_words as f64;
-                println!(
-                    "    {}. {text:<10?} - {count} appearances ({percent:.2}%)",
-                    i + 1
-                );
+            return Ok(());
+        }
+        let res = serde_json::ser::to_string_pretty(value).context("could not serialize")?;
+        write_to_file(out, &res)?;
+        println!("success. writing to {out:?}");
+        Ok(())
+    }
+}
+
+impl Analyze {
+    pub fn run(&self) -> Result<()> {
+        let analyzer = self
+            .analysis
+            .analyzer()?
+            .track_positions(&self.dispersion)
+            .ngram_sizes(
+                DEFAULT_NGRAM_SIZES
+                    .into_iter()
+                    .chain(self.n_grams.iter().map(|&n| n as usize)),
+            );
+        let mut sources = Vec::new();
+        // every source analyzed on its own, for the per file stats and tf-idf
+        let mut documents = Vec::new();
+        let mut processor = if let Some(path) = self.inputs.saved()? {
+            if self.per_file {
+                eprintln!("warning! --per-file needs the texts, a saved analysis has none");
+            }
+            if self.tfidf {
+                eprintln!("warning! --tfidf needs the texts, a saved analysis has none");
             }
+            self.analysis.load(path)?
+        } else if self.per_file || self.tfidf {
+            sources = self.inputs.sources()?;
+            let tables = analyzer.count_sources(&sources)?;
+            documents = tables
+                .iter()
+                .map(|counts| analyzer.finish(counts.clone()))
+                .collect();
+            let mut counts = analyzer.count_table();
+            tables.into_iter().for_each(|table| counts.merge(table));
+            analyzer.finish(counts)?
+        } else {
+            // stream everything
+            analyzer.analyze_sources(&self.inputs.sources()?)?
+        };
+        // a saved analysis keeps the metadata it was made with
+        if processor.metadata.is_none() {
+            processor.metadata = Some(self.analysis.metadata(&self.inputs.paths));
+        }
+        if let Some(language) = processor.language {
+            println!("detected language: {language:?}");
+        }
+        self.per_file(&sources, &documents);
+        self.top(&processor);
+        if self.diversity {
+            print_diversity(&processor);
+        }
+        self.keyness(&processor, &analyzer)?;
+        self.readability(&processor);
+        self.structure(&processor);
+        self.dispersion(&processor, &analyzer);
+        self.laws(&processor)?;
+        self.output.write(&processor)?;
+        if let Some(tfidf) = self.tfidf(&sources, &documents) {
+            self.output.write_beside("tfidf", &tfidf)?;
+        }
+        Ok(())
+    }
+
+    fn top(&self, processor: &WordProcessor) {
+        let Some(num) = self.top.map(|num| num as usize) else {
+            return;
+        };
+        println!();
+        if num > processor.words.len() {
+            println!("the given number exceeds the total word count. continuing anyway");
         }
+        println!("top {num} words:");
+        print_top(&processor.words, num, processor.unique_words);
     }
-    pub fn diversity(&self, processor: &WordProcessor) {
-        if !self.diversity {
+
+    fn readability(&self, processor: &WordProcessor) {
+        if !self.readability {
             return;
         }
-        println!();
+        let readability = &processor.readability;
         println!(
-            "Diversity:\nTotal words: {total}\nUnique words: {unic} ({procent:.1}%)\nToken-Type Ratio: {ratio} ({diversitate})\n",
-            total = processor.total_words,
-            unic = processor.words.len(),
-            procent = processor.ttr * 100.0,
-            ratio = processor.ttr,
-            diversitate = processor.get_variation_string()
+            "\nReadability:\nSentences: {sentences}\nWords: {words} ({per_sentence:.1} per sentence)\nSyllables: {syllables} ({per_word:.2} per word, {poly} words of 3 or more)\n",
+            sentences = readability.sentences,
+            words = readability.words,
+            per_sentence = readability.words as f64 / readability.sentences.max(1) as f64,
+            syllables = readability.syllables,
+            per_word = readability.syllables as f64 / readability.words.max(1) as f64,
+            poly = readability.polysyllables
         );
-        //should never panic
-        let max = processor
-            .words
-            .iter()
-            .max_by(|a, b| a.text.len().cmp(&b.text.len()))
-            .unwrap();
         println!(
-            "Average word length: {len:.2}\nLongest word: \"{cuv}\" ({caractere} characters)\n",
-            len = processor.avglen,
-            cuv = max.text,
-            caractere = max.text.len()
+            "Flesch reading ease: {:.1}\nFlesch-Kincaid grade: {:.1}\nGunning fog: {:.1}\nSMOG: {:.1}\nColeman-Liau: {:.1}",
+            readability.flesch_reading_ease,
+            readability.flesch_kincaid_grade,
+            readability.gunning_fog,
+            readability.smog,
+            readability.coleman_liau
         );
+    }
+
+    fn structure(&self, processor: &WordProcessor) {
+        if !self.structure {
+            return;
+        }
+        let structure = &processor.structure;
         println!(
-            "Rare words (1 appearance): {count}, ({percent:.1}% of vocabilary)",
-            count = processor.rare_words,
-            percent = 100.0 * processor.rare_words as f64 / processor.words.len() as f64
-        )
+            "\nStructure:\nSentences: {}\nParagraphs: {}",
+            structure.sentences, structure.paragraphs
+        );
+        for (name, distribution) in [
+            ("Words per sentence", &structure.sentence_length),
+            ("Words per paragraph", &structure.paragraph_length),
+            ("Sentences per paragraph", &structure.paragraph_sentences),
+        ] {
+            println!(
+                "{name}: mean {:.1}, median {:.1}, min {}, max {}, std dev {:.1}",
+                distribution.mean,
+                distribution.median,
+                distribution.min,
+                distribution.max,
+                distribution.std_dev
+            );
+        }
+        println!(
+            "Longest sentence: sentence {} ({} words)",
+            structure.longest_sentence + 1,
+            structure.sentence_length.max
+        );
+
+        // sentence lengths in buckets of 5 words, the last one holds everything longer
+        let mut buckets = [0usize; 10];
+        for &length in &structure.sentence_lengths {
+            buckets[((length.max(1) - 1) / 5).min(buckets.len() - 1)] += 1;
+        }
+        let most = buckets.iter().copied().max().unwrap_or(0).max(1);
+        println!("\nSentence lengths:");
+        for (i, &count) in buckets.iter().enumerate() {
+            let label = if i == buckets.len() - 1 {
+                format!("{}+", i * 5 + 1)
+            } else {
+                format!("{}-{}", i * 5 + 1, i * 5 + 5)
+            };
+            println!("    {label:>6} | {} {count}", "#".repeat(count * 40 / most));
+        }
     }
-    pub fn out(&self, processor: &WordProcessor) {
-        let Some(out) = self.out.as_ref() else {
+
+    fn dispersion(&self, processor: &WordProcessor, analyzer: &Analyzer) {
+        if self.dispersion.is_empty() {
             return;
-        };
-        match serde_json::ser::to_string_pretty(&processor) {
-            Ok(res) => {
-                println!("success. writing to {out:?}");
-                let _ = write_to_file(out, &res)
-                    .inspect_err(|e| eprintln!("could not write to file: {e}"));
+        }
+        println!("\nDispersion over {} segments:", self.segments);
+        for word in &self.dispersion {
+            // the positions are keyed by the counted (normalized) form
+            let positions = processor
+                .positions
+                .get(&analyzer.normalize(word))
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            print_dispersion(word, positions, processor.total_words, self.segments);
+        }
+    }
+
+    fn laws(&self, processor: &WordProcessor) -> Result<()> {
+        if self.laws {
+            let laws = Laws::new(processor);
+            println!("\nZipf's law (frequency = C * rank^-s):");
+            match laws.zipf {
+                Some(fit) => println!(
+                    "    s = {:.4}, C = {:.2}, R² = {:.4}",
+                    -fit.exponent, fit.coefficient, fit.r_squared
+                ),
+                None => println!("    not enough distinct words to fit"),
             }
-            Err(e) => {
-                eprintln!("could not write to file: {e}");
+            println!("Heaps' law (vocabulary = K * words^β):");
+            match laws.heaps {
+                Some(fit) => println!(
+                    "    β = {:.4}, K = {:.2}, R² = {:.4}",
+                    fit.exponent, fit.coefficient, fit.r_squared
+                ),
+                None => println!("    not enough words to fit"),
             }
         }
+        if let Some(path) = &self.zipf_data {
+            let mut csv = String::from("rank,frequency\n");
+            for (rank, frequency) in rank_frequency(processor) {
+                csv.push_str(&format!("{rank},{frequency}\n"));
+            }
+            write_to_file(path, &csv)?;
+        }
+        if let Some(path) = &self.heaps_data {
+            let mut csv = String::from("tokens,types\n");
+            for point in &processor.vocabulary_growth {
+                csv.push_str(&format!("{},{}\n", point.tokens, point.types));
+            }
+            write_to_file(path, &csv)?;
+        }
+        Ok(())
     }
 
-    // get the word filter
-    pub fn get_word_filter(&self) -> Option<WordFilter> {
-        if !self.analyze_stopwords {
-            if let Some(path) = self.custom_filter.as_ref() {
-                let mut text = String::new();
-                // open the file by path
-                let mut file = File::open(path).expect("could not open the specified filter file");
-                file.read_to_string(&mut text)
-                    .expect("could not read the specified filter file");
-                Some(
-                    // deserialize the json
-                    // possibly add fallback fuzzy plaintext search?
-                    serde_json::from_str(&text).expect(
-                        "could not parse the words from the specified filter file (needs json)",
-                    ),
-                )
-            } else {
-                Some(serde_json::from_str(include_str!("../stop_words.json")).unwrap())
+    fn keyness(&self, processor: &WordProcessor, analyzer: &Analyzer) -> Result<()> {
+        if self.keyness.is_empty() {
+            return Ok(());
+        }
+        let reference = match saved(&self.keyness)? {
+            Some(path) => self.analysis.load(path),
+            None => self
+                .inputs
+                .walker()
+                .and_then(|walker| walker.collect(&self.keyness))
+                .and_then(|sources| analyzer.analyze_sources(&sources))
+                .map_err(anyhow::Error::from),
+        }
+        .context("could not analyze the reference corpus")?;
+        let limit = self.top.unwrap_or(10) as usize;
+        let keyness = Keyness::new(
+            processor,
+            &reference,
+            self.keyness_by,
+            self.significance,
+            limit,
+        );
+        let sides = [
+            ("keywords (over-represented)", &keyness.target),
+            ("negative keywords (under-represented)", &keyness.reference),
+        ];
+        for (title, keywords) in sides {
+            println!("\n{title}:");
+            for (i, keyword) in keywords.iter().enumerate() {
+                println!(
+                    "    {}. {text:<16?} {target} vs {reference} - G² {ll:.2}, χ² {chi:.2}, %DIFF {diff:.1}",
+                    i + 1,
+                    text = keyword.text,
+                    target = keyword.target_count,
+                    reference = keyword.reference_count,
+                    ll = keyword.log_likelihood,
+                    chi = keyword.chi_square,
+                    diff = keyword.percent_diff
+                );
             }
-        } else {
-            None
         }
+        Ok(())
     }
 
-    pub fn handle_commands(&self) {
-        let filter = self.get_word_filter();
-        // these two are always mutually exclusive due to command parsing
-        if let Some([p1, p2]) = self.file_args.compare.as_ref().map(|v| &v[..2]) {
-            // quick check for unused commands
-            [
-                (self.analyze_stopwords, "analyze_stopwords"),
-                (self.top.is_some(), "top"),
-                (self.n_grams.is_some(), "n_grams"),
-                (self.concordance.is_some(), "concordance"),
-                (self.out.is_some(), "out"),
-                (self.cloud, "cloud"),
-            ]
-            .iter()
-            .filter_map(|(is, var)| if *is { Some(var) } else { None })
-            .for_each(|var| println!("warning! {var} has no effect in compare mode"));
-            let (mut f1, mut f2) = (
-                File::open(p1).expect("could not open the first file"),
-                File::open(p2).expect("could not open the second file"),
-            );
-            let mut data = String::new();
-            f1.read_to_string(&mut data)
-                .expect("could not read the first file");
-            if data.is_empty() {
-                eprintln!("there is no text to analyze");
-                return;
-            }
-            let processor1 = WordProcessor::from_str(&data, &filter);
-            f2.read_to_string(&mut data)
-                .expect("could not read the first file");
-            if data.is_empty() {
-                eprintln!("there is no text to analyze");
-                return;
+    fn per_file(&self, sources: &[Source], documents: &[wordfreq::Result<WordProcessor>]) {
+        // a saved analysis has no documents
+        if !self.per_file || documents.is_empty() {
+            return;
+        }
+        println!("\nper file:");
+        for (source, processor) in sources.iter().zip(documents) {
+            match processor {
+                Ok(processor) => println!(
+                    "    {source} - {total} words, {unique} unique ({ttr:.3} TTR){language}",
+                    total = processor.total_words,
+                    unique = processor.unique_words,
+                    ttr = processor.ttr,
+                    language = processor
+                        .language
+                        .map(|language| format!(", {language:?}"))
+                        .unwrap_or_default()
+                ),
+                Err(e) => println!("    {source} - {e}"),
             }
-            let processor2 = WordProcessor::from_str(&data, &filter);
-
-            // there was no example on what to do with compare
-            // so I will take some liberties
-            self.diversity(&processor1);
-            println!();
-            self.diversity(&processor2);
-            println!();
+        }
+    }
 
-            match processor1
-                .avglen
-                .partial_cmp(&processor2.avglen)
-                .expect("could not compare the texts properly")
-            {
-                std::cmp::Ordering::Less => {
-                    println!("the first text has lower average word length")
-                }
-                std::cmp::Ordering::Equal => println!("the texts have equal average word length"),
-                std::cmp::Ordering::Greater => {
-                    println!("the first text has greater average word length")
-                }
+    fn tfidf(
+        &self,
+        sources: &[Source],
+        documents: &[wordfreq::Result<WordProcessor>],
+    ) -> Option<TfIdf> {
+        // a saved analysis has no documents
+        if !self.tfidf || documents.is_empty() {
+            return None;
+        }
+        // empty documents have no terms to rank
+        let documents = sources
+            .iter()
+            .zip(documents)
+            .filter_map(|(source, processor)| Some((source.to_string(), processor.as_ref().ok()?)))
+            .collect::<Vec<_>>();
+        let limit = self.top.unwrap_or(10) as usize;
+        let tfidf = TfIdf {
+            metadata: Some(self.analysis.metadata(&self.inputs.paths)),
+            ..TfIdf::new(&documents, self.tf, self.idf, limit)
+        };
+        for document in &tfidf.documents {
+            println!("\ntop {limit} tf-idf terms of {}:", document.name);
+            for (i, term) in document.terms.iter().enumerate() {
+                println!(
+                    "    {}. {text:<10?} - {score:.4} ({count} appearances, in {df} documents)",
+                    i + 1,
+                    text = term.text,
+                    score = term.score,
+                    count = term.count,
+                    df = term.document_frequency
+                );
             }
+        }
+        Some(tfidf)
+    }
+}
 
-            match processor1
-                .ttr
-                .partial_cmp(&processor2.ttr)
-                .expect("could not compare the texts")
-            {
-                std::cmp::Ordering::Less => {
-                    println!("the first text has lower word diversity")
-                }
-                std::cmp::Ordering::Equal => println!("the texts have equal word diversity"),
-                std::cmp::Ordering::Greater => {
-                    println!("the first text has greater word diversity")
+impl Compare {
+    pub fn run(&self) -> Result<()> {
+        let analyzer = self.analysis.analyzer()?;
+        // every file is its own text, a directory gives one per file directly inside it
+        let sources = SourceWalker::new()
+            .collect(&self.paths)
+            .context("could not collect the input files")?;
+        // saved analyses are loaded, the texts analyzed (in parallel) and both put back in order
+        let texts = sources
+            .iter()
+            .filter(|source| saved_path(source).is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut tables = analyzer.count_sources(&texts)?.into_iter();
+        let processors = sources
+            .iter()
+            .map(|source| match saved_path(source) {
+                Some(path) => self.analysis.load(path),
+                None => {
+                    let table = tables
+                        .next()
+                        .expect("count_sources gives one table per text");
+                    analyzer
+                        .finish(table)
+                        .with_context(|| format!("could not analyze {source}"))
                 }
-            }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let names = sources.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let limit = self.top;
+        let comparison = Comparison {
+            metadata: Some(self.analysis.metadata(&self.paths)),
+            ..Comparison::new(names, &processors, limit)
+        };
 
-            match processor1.total_words.cmp(&processor2.total_words) {
-                std::cmp::Ordering::Less => {
-                    println!("the first text has less total words")
-                }
-                std::cmp::Ordering::Equal => println!("the texts have an equal total word count"),
-                std::cmp::Ordering::Greater => {
-                    println!("the first text has more total words")
-                }
+        if self.diversity {
+            for (name, processor) in comparison.names.iter().zip(&processors) {
+                println!("\n{name}:");
+                print_diversity(processor);
             }
+        }
 
-            match processor1.rare_words.cmp(&processor2.rare_words) {
-                std::cmp::Ordering::Less => {
-                    println!("the first text has less rare words")
-                }
-                std::cmp::Ordering::Equal => println!("the texts have equal rare word counts"),
-                std::cmp::Ordering::Greater => {
-                    println!("the first text has more rare words")
-                }
+        println!("\ntexts:");
+        for (i, name) in comparison.names.iter().enumerate() {
+            println!("    {}. {name}", i + 1);
+        }
+        println!();
+        print!("{:<22}", "");
+        for i in 1..=comparison.names.len() {
+            print!("{:<22}", i);
+        }
+        println!();
+        for metric in &comparison.metrics {
+            print!("{:<22}", metric.name);
+            for (i, (value, delta)) in metric.values.iter().zip(&metric.deltas).enumerate() {
+                let cell = if i == 0 {
+                    format_number(*value)
+                } else {
+                    let sign = if *delta < 0.0 { "" } else { "+" };
+                    format!(
+                        "{} ({sign}{})",
+                        format_number(*value),
+                        format_number(*delta)
+                    )
+                };
+                print!("{cell:<22}");
             }
+            println!();
+        }
 
-            match processor1.words.len().cmp(&processor2.words.len()) {
-                std::cmp::Ordering::Less => {
-                    println!("the first text has less unique words")
-                }
-                std::cmp::Ordering::Equal => println!("the texts have equal unique word counts"),
-                std::cmp::Ordering::Greater => {
-                    println!("the first text has more unique words")
-                }
-            }
-        } else if let Some(path) = &self.file_args.analyze {
-            let mut f = File::open(path).expect("could not open the specified file");
-            let mut data = String::new();
-            f.read_to_string(&mut data).expect("could not read file");
-
-            // don't waste time processing nothing
-            if data.is_empty() {
-                eprintln!("there is no text to analyze");
-                return;
+        println!(
+            "\nshared vocabulary: {} words in every text",
+            comparison.shared.len()
+        );
+        println!(
+            "    {}",
+            comparison
+                .shared
+                .iter()
+                .take(limit)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        for (i, exclusive) in comparison.exclusive.iter().enumerate() {
+            println!("only in {}: {} words", i + 1, exclusive.len());
+            println!(
+                "    {}",
+                exclusive
+                    .iter()
+                    .take(limit)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        println!("\nlargest frequency differences (per 10 000 words):");
+        for difference in &comparison.differences {
+            print!("    {:<16}", difference.text);
+            for rate in &difference.rates {
+                print!("{rate:>10.2}");
             }
-            let processor = WordProcessor::from_str(&data, &filter);
-            self.top(&processor);
-            self.diversity(&processor);
-            self.out(&processor);
-            self.concordance(&data);
-            self.cloud(&processor);
-        };
+            println!();
+        }
+        self.output.write(&comparison)?;
+        Ok(())
     }
+}
 
-    pub fn concordance(&self, haystack: &str) {
-        let Some(needle) = self.concordance.as_ref() else {
-            return;
-        };
-
-        // turn the haystack into a vec of words
-        let words = haystack.split_whitespace().collect::<Vec<_>>();
+impl ConcordanceCommand {
+    pub fn run(&self) -> Result<()> {
+        let analyzer = Analyzer::new().tokenizer(self.tokenizer.build()?);
+        let context = &self.context;
+        let concordance = Concordance::new(&self.query, context.match_kind)
+            .with_context(|| format!("could not search for {:?}", self.query))?
+            .context(context.left, context.right);
+        let texts = self.inputs.read()?;
+        let mut results = Vec::new();
+        for (source, text) in &texts {
+            if texts.len() > 1 {
+                println!("{source}:");
+            }
+            let mut hits = concordance.search(text, &analyzer);
+            sort_hits(&mut hits, context.sort);
+            print_hits(&hits, context.max);
+            results.push(SourceHits {
+                source: source.clone(),
+                hits,
+            });
+        }
+        if let Some(path) = &self.out {
+            let json = serde_json::to_string_pretty(&results)?;
+            write_to_file(path, &json)?;
+        }
+        Ok(())
+    }
+}
 
-        let word_count = words.len();
-        // save all indices wherein the word is found
-        let v = words
+impl Collocates {
+    pub fn run(&self) -> Result<()> {
+        let analyzer = self.analysis.analyzer()?;
+        let texts = self.inputs.read()?;
+        let mut counts = analyzer.count_table();
+        for (_, text) in &texts {
+            let mut table = analyzer.count_table();
+            analyzer.count_into(text, &mut table);
+            counts.merge(table);
+        }
+        let processor = analyzer.finish(counts)?;
+        let documents = texts
             .iter()
-            .enumerate()
-            .filter(|(_, word)| word.to_lowercase().contains(needle))
-            .map(|(i, _)| i)
+            .map(|(_, text)| analyzer.counted_words(text))
             .collect::<Vec<_>>();
+        let rank = &self.rank;
+        let collocates = collocates(
+            documents.iter().map(Vec::as_slice),
+            &analyzer.normalize(&self.word),
+            (self.span_left, self.span_right),
+            &processor,
+            rank.min_count,
+            rank.rank_by,
+        );
+        println!(
+            "top {num} collocates of {node:?} ({} left, {} right) by {measure}:",
+            self.span_left,
+            self.span_right,
+            num = rank.top,
+            node = self.word,
+            measure = rank.measure()
+        );
+        for (i, collocate) in collocates.iter().take(rank.top).enumerate() {
+            println!(
+                "    {}. {text:<10?} - {count} times ({left} left, {right} right, {frequency} in total), MI {mi:.2}, t-score {t:.2}, log-likelihood {ll:.2}",
+                i + 1,
+                text = collocate.text,
+                count = collocate.count,
+                left = collocate.left,
+                right = collocate.right,
+                frequency = collocate.frequency,
+                mi = collocate.mi,
+                t = collocate.t_score,
+                ll = collocate.log_likelihood
+            );
+        }
+        Ok(())
+    }
+}
 
-        // go through every index
-        for &i in v.iter().take(self.max.unwrap_or(v.len())) {
-            // compute whether there's more words to the left and right
-            let overshoot = i + 2 > word_count;
-            let undershoot = i < 1;
-            // get the min and max indices
-            let min_i = (i - 3).max(0);
-            let max_i = (i + 3).min(word_count);
-            if !undershoot {
-                print!("...");
-            }
-
-            let mut first = true;
-            // clippy sniped me here
-            // (word) -> (i, word) -> (i, word)[0..max_i] -> (i,word)[min_i..max_i]
-            for (j, word) in words.iter().enumerate().take(max_i).skip(min_i) {
-                if first {
-                    if j == i {
-                        print!("*{}*", word);
-                    } else {
-                        print!("{}", word);
-                    }
+impl RankArgs {
+    // name of the association measure
+    fn measure(&self) -> String {
+        self.rank_by
+            .to_possible_value()
+            .map_or_else(String::new, |value| value.get_name().to_string())
+    }
+}
 
-                    first = false;
-                } else if j == i {
-                    print!(" *{}*", word);
-                } else {
-                    print!(" {}", word);
-                }
+impl Ngrams {
+    pub fn run(&self) -> Result<()> {
+        let analyzer = self
+            .analysis
+            .analyzer()?
+            .ngram_sizes(self.sizes.iter().map(|&n| n as usize));
+        let processor = self.inputs.analyze(&self.analysis, &analyzer)?;
+        let num = self.rank.top;
+        for (i, &n) in self.sizes.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            let n = n as usize;
+            if self.rank.rank_by != AssociationMeasure::Count {
+                self.top_collocations(&processor, n);
+                continue;
             }
-            if !overshoot {
-                print!("...");
+            let ngrams = processor.ngrams(n);
+            if num > ngrams.len() {
+                println!("the given number exceeds the total {n}-gram count. continuing anyway");
             }
+            println!("top {num} {n}-grams:");
+            print_top(ngrams, num, processor.unique_words);
+        }
+        Ok(())
+    }
 
-            println!();
+    fn top_collocations(&self, processor: &WordProcessor, n: usize) {
+        let rank = &self.rank;
+        let num = rank.top;
+        let collocations = collocations(processor, n, rank.min_count, rank.rank_by);
+        if num > collocations.len() {
+            println!("the given number exceeds the total {n}-gram count. continuing anyway");
         }
-        if let Some(max) = self.max
-            && max < v.len()
-        {
-            println!("...and {} more", v.len() - max);
+        println!("top {num} {n}-grams by {}:", rank.measure());
+        for (i, collocation) in collocations.iter().take(num).enumerate() {
+            println!(
+                "    {}. {text:<10?} - {score:.3} ({count} appearances)",
+                i + 1,
+                text = collocation.text,
+                score = collocation.score(rank.rank_by),
+                count = collocation.count
+            );
         }
     }
-    pub fn cloud(&self, processor: &WordProcessor) {
-        if !self.cloud {
-            return;
-        }
+}
+
+impl Cloud {
+    pub fn run(&self) -> Result<()> {
+        let processor = self
+            .inputs
+            .analyze(&self.analysis, &self.analysis.analyzer()?)?;
         let width = self.width;
         let mut rng = rand::rng();
 
@@ -419,13 +1319,211 @@ impl Commands {
             }
         }
         println!();
+        Ok(())
     }
 }
+
+impl IndexCommand {
+    pub fn run(&self) -> Result<()> {
+        let analyzer = self.analysis.analyzer()?;
+        let documents = self.inputs.read()?;
+        let metadata = self.analysis.metadata(&self.inputs.paths);
+        let file = File::create(&self.out)
+            .with_context(|| format!("could not create the index {}", self.out.display()))?;
+        let mut file = BufWriter::new(file);
+        Index::write(&analyzer, &documents, metadata, &mut file)
+            .context("could not index the texts")?;
+        file.flush()?;
+        println!("indexed {} texts into {:?}", documents.len(), self.out);
+        Ok(())
+    }
+}
+
+impl QueryCommand {
+    pub fn run(&self) -> Result<()> {
+        let index = Index::open(&self.index)
+            .with_context(|| format!("could not open the index {}", self.index.display()))?;
+        match &self.query {
+            IndexQuery::Top { top, n_grams: None } => {
+                println!("top {top} words:");
+                print_top(&index.top_words(*top), *top, index.unique_words());
+            }
+            IndexQuery::Top {
+                top,
+                n_grams: Some(n),
+            } => {
+                let ngrams = index.top_ngrams(*n, *top);
+                if ngrams.is_empty() {
+                    println!("no {n}-grams were indexed");
+                    return Ok(());
+                }
+                println!("top {top} {n}-grams:");
+                print_top(&ngrams, *top, index.unique_words());
+            }
+            IndexQuery::Concordance { query, context } => {
+                let hits = index
+                    .concordance(query, context.match_kind, (context.left, context.right))
+                    .with_context(|| format!("could not search for {query:?}"))?;
+                let several = hits.len() > 1;
+                for (name, mut hits) in index.documents().zip(hits) {
+                    if several {
+                        println!("{name}:");
+                    }
+                    sort_hits(&mut hits, context.sort);
+                    print_hits(&hits, context.max);
+                }
+            }
+            IndexQuery::Dispersion { words, segments } => {
+                println!("Dispersion over {segments} segments:");
+                for word in words {
+                    let positions = index.positions(word);
+                    print_dispersion(word, &positions, index.total_words(), *segments);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn print_diversity(processor: &WordProcessor) {
+    println!();
+    println!(
+        "Diversity:\nTotal words: {total}\nUnique words: {unic} ({procent:.1}%)\nToken-Type Ratio: {ratio} ({diversitate})\n",
+        total = processor.total_words,
+        unic = processor.words.len(),
+        procent = processor.ttr * 100.0,
+        ratio = processor.ttr,
+        diversitate = processor.get_variation_string()
+    );
+    let diversity = &processor.diversity;
+    println!(
+        "Length-robust measures:\nMATTR: {mattr:.4}\nMTLD: {mtld:.2}\nHD-D: {hdd:.4}\nYule's K: {k:.2}\nHerdan's C: {c:.4}\nGuiraud's R: {r:.2}\nSimpson's D: {d:.5}\n",
+        mattr = diversity.mattr,
+        mtld = diversity.mtld,
+        hdd = diversity.hdd,
+        k = diversity.yules_k,
+        c = diversity.herdan_c,
+        r = diversity.guiraud_r,
+        d = diversity.simpson_d
+    );
+    //should never panic
+    let max = processor
+        .words
+        .iter()
+        .max_by(|a, b| a.text.len().cmp(&b.text.len()))
+        .unwrap();
+    println!(
+        "Average word length: {len:.2}\nLongest word: \"{cuv}\" ({caractere} characters)\n",
+        len = processor.avglen,
+        cuv = max.text,
+        caractere = max.text.len()
+    );
+    println!(
+        "Rare words (1 appearance): {count}, ({percent:.1}% of vocabilary)",
+        count = processor.rare_words,
+        percent = 100.0 * processor.rare_words as f64 / processor.words.len() as f64
+    )
+}
+
+// the first `max` hits as aligned keyword in context columns
+fn print_hits(hits: &[Hit], max: Option<usize>) {
+    let shown = &hits[..max.unwrap_or(hits.len()).min(hits.len())];
+    let left_width = shown
+        .iter()
+        .map(|hit| hit.left.chars().count())
+        .max()
+        .unwrap_or(0);
+    let keyword_width = shown
+        .iter()
+        .map(|hit| hit.keyword.chars().count())
+        .max()
+        .unwrap_or(0);
+    for hit in shown {
+        println!(
+            "{left:>left_width$}  {keyword:<keyword_width$}  {right}",
+            left = hit.left,
+            keyword = hit.keyword,
+            right = hit.right
+        );
+    }
+    if shown.len() < hits.len() {
+        println!("...and {} more", hits.len() - shown.len());
+    }
+}
+
+// the dispersion of `word` over `segments` parts of the text, with its barcode
+fn print_dispersion(word: &str, positions: &[usize], total_words: usize, segments: u16) {
+    let Some(dispersion) = Dispersion::new(word, positions, total_words, segments as usize) else {
+        println!("    {word:?} was never counted");
+        return;
+    };
+    println!(
+        "    {word:?} - {count} appearances, in {range}/{segments} segments, Juilland's D {d:.3}, Gries' DP {dp:.3}",
+        count = dispersion.count,
+        range = dispersion.range,
+        d = dispersion.juilland_d,
+        dp = dispersion.gries_dp
+    );
+    println!("    [{}]", barcode(positions, total_words, BARCODE_WIDTH));
+}
+
+// numbered list of the first `num` entries
+fn print_top(data: &[WordData], num: usize, unique_words: usize) {
+    for (i, WordData { text, count, .. }) in data.iter().take(num).enumerate() {
+        let percent = 100.0 * *count as f64 / unique_words as f64;
+        println!(
+            "    {}. {text:<10?} - {count} appearances ({percent:.2}%)",
+            i + 1
+        );
+    }
+}
+
+// integers without decimals, ratios with a few
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{value}")
+    } else {
+        format!("{value:.3}")
+    }
+}
+
+// saved analyses are given by the path of their json file
+fn is_saved(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+fn saved_path(source: &Source) -> Option<&Path> {
+    match source {
+        Source::Path(path) if is_saved(path) => Some(path),
+        _ => None,
+    }
+}
+
+// `path` with `-name` added to its file stem
+fn sibling(path: &Path, name: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    let file_name = format!("{stem}-{name}.{extension}");
+    path.with_file_name(file_name.trim_end_matches('.'))
+}
+
+// the saved analysis `paths` is, a saved analysis can't be mixed with texts
+fn saved(paths: &[PathBuf]) -> Result<Option<&Path>> {
+    match paths {
+        [path] if is_saved(path) => Ok(Some(path)),
+        paths if paths.iter().any(|path| is_saved(path)) => Err(anyhow!(
+            "a saved analysis can only be loaded on its own, not with other texts"
+        )),
+        _ => Ok(None),
+    }
+}
+
 //exactly what it says on the tin, take a path, some data, write data to file
-fn write_to_file(path: &PathBuf, data: &str) -> Result<()> {
+fn write_to_file(path: &Path, data: &str) -> Result<()> {
     let mut opts = OpenOptions::new();
     opts.write(true).truncate(true).create(true);
-    let mut file = opts.open(path)?;
-    file.write_all(data.as_bytes())?;
-    Ok(())
+    opts.open(path)
+        .and_then(|mut file| file.write_all(data.as_bytes()))
+        .with_context(|| format!("could not write to {}", path.display()))
 }
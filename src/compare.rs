@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::data::{FORMAT_VERSION, Metadata, WordProcessor};
+
+/// One statistic across every compared text
+#[derive(Serialize, JsonSchema)]
+pub struct Metric {
+    /// Name of the statistic, eg. `type-token ratio`
+    pub name: &'static str,
+    /// Value for every text, in the order of the names
+    pub values: Vec<f64>,
+    /// Difference from the first text
+    pub deltas: Vec<f64>,
+}
+
+impl Metric {
+    fn new(name: &'static str, values: Vec<f64>) -> Self {
+        let first = values.first().copied().unwrap_or_default();
+        let deltas = values.iter().map(|value| value - first).collect();
+        Self {
+            name,
+            values,
+            deltas,
+        }
+    }
+}
+
+/// How often a word appears in every compared text
+#[derive(Serialize, JsonSchema)]
+pub struct WordDifference {
+    pub text: String,
+    /// Occurrences in every text
+    pub counts: Vec<usize>,
+    /// Occurrences per 10 000 words
+    pub rates: Vec<f64>,
+    /// Highest rate minus the lowest one
+    pub spread: f64,
+}
+
+/// N-way comparison of independently analyzed texts
+#[derive(Serialize, JsonSchema)]
+pub struct Comparison {
+    /// Format version of the comparison ([`FORMAT_VERSION`])
+    pub version: u32,
+    /// Where and how the texts were analyzed, set by the command line tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    /// The compared texts
+    pub names: Vec<String>,
+    pub metrics: Vec<Metric>,
+    /// Words found in every text, most frequent first
+    pub shared: Vec<String>,
+    /// For every text, the words found only in it, most frequent first
+    pub exclusive: Vec<Vec<String>>,
+    /// Words with the largest differences in relative frequency
+    pub differences: Vec<WordDifference>,
+}
+
+impl Comparison {
+    /// Compare the texts, keeping the `limit` largest frequency differences
+    pub fn new(names: Vec<String>, processors: &[WordProcessor], limit: usize) -> Self {
+        let metric = |name, f: fn(&WordProcessor) -> f64| {
+            Metric::new(name, processors.iter().map(f).collect())
+        };
+        let metrics = vec![
+            metric("total words", |p| p.total_words as f64),
+            metric("unique words", |p| p.unique_words as f64),
+            metric("rare words", |p| p.rare_words as f64),
+            metric("type-token ratio", |p| p.ttr),
+            metric("average word length", |p| p.avglen),
+            metric("MATTR", |p| p.diversity.mattr),
+            metric("MTLD", |p| p.diversity.mtld),
+            metric("HD-D", |p| p.diversity.hdd),
+            metric("Yule's K", |p| p.diversity.yules_k),
+            metric("Herdan's C", |p| p.diversity.herdan_c),
+            metric("Guiraud's R", |p| p.diversity.guiraud_r),
+            metric("Simpson's D", |p| p.diversity.simpson_d),
+            metric("sentences", |p| p.readability.sentences as f64),
+            metric("Flesch reading ease", |p| p.readability.flesch_reading_ease),
+            metric("Flesch-Kincaid grade", |p| {
+                p.readability.flesch_kincaid_grade
+            }),
+            metric("Gunning fog", |p| p.readability.gunning_fog),
+            metric("SMOG", |p| p.readability.smog),
+            metric("Coleman-Liau", |p| p.readability.coleman_liau),
+        ];
+
+        // word -> count in every text
+        let mut vocabulary: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for (i, processor) in processors.iter().enumerate() {
+            for word in &processor.words {
+                vocabulary
+                    .entry(&word.text)
+                    .or_insert_with(|| vec![0; processors.len()])[i] = word.count;
+            }
+        }
+
+        let mut shared = vocabulary
+            .iter()
+            .filter(|(_, counts)| counts.iter().all(|&count| count > 0))
+            .map(|(&text, counts)| (text, counts.iter().sum::<usize>()))
+            .collect::<Vec<_>>();
+        // stable sort, ties stay alphabetical
+        shared.sort_by_key(|&(_, total)| std::cmp::Reverse(total));
+        let shared = shared.into_iter().map(|(text, _)| text.into()).collect();
+
+        let mut exclusive = vec![Vec::new(); processors.len()];
+        for (&text, counts) in &vocabulary {
+            let mut present = counts.iter().enumerate().filter(|(_, count)| **count > 0);
+            if let (Some((i, &count)), None) = (present.next(), present.next()) {
+                exclusive[i].push((text, count));
+            }
+        }
+        let exclusive = exclusive
+            .into_iter()
+            .map(|mut words| {
+                words.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+                words.into_iter().map(|(text, _)| text.into()).collect()
+            })
+            .collect();
+
+        let mut differences = vocabulary
+            .into_iter()
+            .map(|(text, counts)| {
+                let rates = counts
+                    .iter()
+                    .zip(processors)
+                    .map(|(&count, p)| 10_000.0 * count as f64 / p.total_words as f64)
+                    .collect::<Vec<_>>();
+                let max = rates.iter().copied().fold(f64::MIN, f64::max);
+                let min = rates.iter().copied().fold(f64::MAX, f64::min);
+                WordDifference {
+                    text: text.into(),
+                    counts,
+                    rates,
+                    spread: max - min,
+                }
+            })
+            .collect::<Vec<_>>();
+        differences.sort_by(|a, b| b.spread.total_cmp(&a.spread));
+        differences.truncate(limit);
+
+        Self {
+            version: FORMAT_VERSION,
+            metadata: None,
+            names,
+            metrics,
+            shared,
+            exclusive,
+            differences,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Analyzer;
+
+    #[test]
+    fn texts_are_compared_independently() {
+        let analyzer = Analyzer::new();
+        let processors = ["cat dog dog", "dog bird", "dog dog dog fish"]
+            .map(|text| analyzer.analyze(text).unwrap());
+        let comparison = Comparison::new(vec!["a".into(), "b".into(), "c".into()], &processors, 2);
+
+        assert_eq!(comparison.metrics[0].values, [3.0, 2.0, 4.0]);
+        assert_eq!(comparison.metrics[0].deltas, [0.0, -1.0, 1.0]);
+        assert_eq!(comparison.shared, ["dog"]);
+        assert_eq!(comparison.exclusive, [["cat"], ["bird"], ["fish"]]);
+        assert_eq!(comparison.differences.len(), 2);
+        assert_eq!(comparison.differences[0].text, "bird");
+    }
+}
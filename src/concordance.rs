@@ -0,0 +1,227 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{analyzer::Analyzer, error::Result};
+
+/// How the query is matched against the words of the text, always ignoring case
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchKind {
+    /// The whole word, "he" doesn't match "the"
+    #[default]
+    Exact,
+    /// Words starting with the query
+    Prefix,
+    /// Words entirely matched by the regular expression
+    Regex,
+    /// Consecutive words, the query is split with the analyzer's tokenizer
+    Phrase,
+}
+
+/// Order of the hits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortBy {
+    /// Order of appearance
+    #[default]
+    Position,
+    /// Alphabetically by the words to the left, nearest first
+    Left,
+    /// Alphabetically by the words to the right
+    Right,
+    /// Alphabetically by the matched text
+    Keyword,
+}
+
+/// One occurrence of the query with its context
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Hit {
+    /// Byte offset of the match in the text
+    pub start: usize,
+    /// Byte offset right after the match
+    pub end: usize,
+    /// Index of the first matched word
+    pub position: usize,
+    /// Context before the match, whitespace collapsed
+    pub left: String,
+    /// The matched text as written
+    pub keyword: String,
+    /// Context after the match, whitespace collapsed
+    pub right: String,
+}
+
+impl Hit {
+    /// The hit of `text[start..end]` at word `position`, with the context `text[left..start]` and `text[end..right]`
+    pub(crate) fn new(
+        text: &str,
+        (left, start, end, right): (usize, usize, usize, usize),
+        position: usize,
+    ) -> Self {
+        Self {
+            start,
+            end,
+            position,
+            left: collapse(&text[left..start]),
+            keyword: collapse(&text[start..end]),
+            right: collapse(&text[end..right]),
+        }
+    }
+}
+
+enum Query {
+    Exact(String),
+    Prefix(String),
+    Regex(Regex),
+    Phrase(String),
+}
+
+/// Keyword in context search
+///
+/// ```
+/// use wordfreq::{Analyzer, Concordance, concordance::MatchKind};
+///
+/// let concordance = Concordance::new("cat", MatchKind::Exact).unwrap().context(1, 1);
+/// let hits = concordance.search("the cat sat", &Analyzer::new());
+/// assert_eq!((hits[0].left.as_str(), hits[0].right.as_str()), ("the", "sat"));
+/// ```
+pub struct Concordance {
+    query: Query,
+    left: usize,
+    right: usize,
+}
+
+impl Concordance {
+    /// A search for `query` with 5 words of context on each side
+    ///
+    /// # Errors
+    /// [`crate::Error::Regex`] if a regex query doesn't compile
+    pub fn new(query: &str, kind: MatchKind) -> Result<Self> {
+        let query = match kind {
+            MatchKind::Exact => Query::Exact(query.to_lowercase()),
+            MatchKind::Prefix => Query::Prefix(query.to_lowercase()),
+            MatchKind::Regex => Query::Regex(word_regex(query)?),
+            MatchKind::Phrase => Query::Phrase(query.to_lowercase()),
+        };
+        Ok(Self {
+            query,
+            left: 5,
+            right: 5,
+        })
+    }
+
+    /// Set how many words of context are kept on each side
+    pub fn context(mut self, left: usize, right: usize) -> Self {
+        self.left = left;
+        self.right = right;
+        self
+    }
+
+    /// Every occurrence in `text`, split with the analyzer's tokenizer
+    pub fn search(&self, text: &str, analyzer: &Analyzer) -> Vec<Hit> {
+        let tokens = analyzer.tokens(text);
+        let words = tokens
+            .iter()
+            .map(|(_, word)| word.to_lowercase())
+            .collect::<Vec<_>>();
+        let phrase = match &self.query {
+            Query::Phrase(phrase) => analyzer
+                .tokens(phrase)
+                .into_iter()
+                .map(|(_, word)| word)
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let mut hits = Vec::new();
+        for i in 0..words.len() {
+            let len = match &self.query {
+                Query::Exact(query) => usize::from(words[i] == *query),
+                Query::Prefix(query) => usize::from(words[i].starts_with(query.as_str())),
+                Query::Regex(regex) => usize::from(regex.is_match(tokens[i].1)),
+                Query::Phrase(_) => {
+                    let matched = !phrase.is_empty()
+                        && words.get(i..i + phrase.len()).is_some_and(|window| {
+                            window
+                                .iter()
+                                .zip(&phrase)
+                                .all(|(word, query)| word == query)
+                        });
+                    if matched { phrase.len() } else { 0 }
+                }
+            };
+            if len == 0 {
+                continue;
+            }
+            let last = i + len - 1;
+            let start = tokens[i].0;
+            let end = tokens[last].0 + tokens[last].1.len();
+            let left_start = tokens[i.saturating_sub(self.left)].0;
+            let right_end = match (last + self.right).min(tokens.len() - 1) {
+                j if j > last => tokens[j].0 + tokens[j].1.len(),
+                _ => end,
+            };
+            hits.push(Hit::new(text, (left_start, start, end, right_end), i));
+        }
+        hits
+    }
+}
+
+/// Sort the hits, ties keep their order of appearance
+pub fn sort_hits(hits: &mut [Hit], by: SortBy) {
+    match by {
+        SortBy::Position => hits.sort_by_key(|hit| hit.start),
+        SortBy::Left => hits.sort_by_cached_key(|hit| {
+            hit.left
+                .to_lowercase()
+                .split_whitespace()
+                .rev()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        }),
+        SortBy::Right => hits.sort_by_cached_key(|hit| hit.right.to_lowercase()),
+        SortBy::Keyword => hits.sort_by_cached_key(|hit| hit.keyword.to_lowercase()),
+    }
+}
+
+/// A case insensitive regex matching whole words only
+pub(crate) fn word_regex(query: &str) -> Result<Regex> {
+    Ok(RegexBuilder::new(&format!("^(?:{query})$"))
+        .case_insensitive(true)
+        .build()?)
+}
+
+// trim and turn every run of whitespace into one space
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn queries_match_whole_words() {
+        let text = "He said, the hero and the\nheroine left. Then he slept.";
+        let analyzer = Analyzer::new();
+        let search = |query, kind| {
+            Concordance::new(query, kind)
+                .unwrap()
+                .context(2, 1)
+                .search(text, &analyzer)
+        };
+
+        let exact = search("he", MatchKind::Exact);
+        assert_eq!(exact.len(), 2);
+        assert_eq!((exact[0].start, exact[0].end), (0, 2));
+        // first word, nothing to the left
+        assert_eq!(exact[0].left, "");
+        assert_eq!(exact[1].left, "left. Then");
+
+        assert_eq!(search("her", MatchKind::Prefix).len(), 2);
+        assert_eq!(search("hero(ine)?", MatchKind::Regex).len(), 2);
+
+        let phrase = search("the heroine", MatchKind::Phrase);
+        assert_eq!(phrase[0].keyword, "the heroine");
+        assert_eq!(phrase[0].right, "left");
+    }
+}
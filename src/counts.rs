@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::{
+    diversity::SequenceStats, language::Language, readability::TextStats, sentence::Segmenter,
+};
+
+/// Count of one word or n-gram
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub count: usize,
+    /// Index of the first occurrence, among the counted words for words
+    /// and among all tokens for n-grams
+    pub first: usize,
+}
+
+/// Incremental word and n-gram counts
+///
+/// Tokens are pushed one at a time, only the last few tokens are kept around
+/// for the n-grams. Besides the vocabulary the memory grows with the number of
+/// sentences and paragraphs (one length each) and the positions of tracked words
+#[derive(Clone, Debug, Default)]
+pub struct CountTable {
+    pub(crate) words: HashMap<String, Entry>,
+    pub(crate) ngrams: BTreeMap<usize, HashMap<String, Entry>>,
+    /// Surface forms counted under each normalized word
+    pub(crate) forms: HashMap<String, BTreeMap<String, usize>>,
+    /// Counted (non stopword) tokens
+    pub(crate) total_words: usize,
+    /// Every token pushed, stopwords included
+    pub(crate) tokens_seen: usize,
+    /// Detected language, `None` if unknown or the documents are in different languages
+    pub(crate) language: Option<Language>,
+    /// State of the order dependent diversity measures
+    pub(crate) sequence: SequenceStats,
+    /// Every token, stopwords included, for the readability formulas
+    pub(crate) text: TextStats,
+    pub(crate) segmenter: Segmenter,
+    /// Indices among the counted words of the tracked words
+    pub(crate) positions: HashMap<String, Vec<usize>>,
+    // the last tokens and whether they are stopwords
+    window: VecDeque<(String, bool)>,
+}
+
+impl CountTable {
+    /// An empty table computing the n-grams of the given sizes, sizes below 2 are ignored
+    pub fn new(ngram_sizes: &[usize]) -> Self {
+        Self {
+            ngrams: ngram_sizes
+                .iter()
+                .filter(|&&n| n >= 2)
+                .map(|&n| (n, HashMap::new()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Record where the given (normalized) words occur
+    pub fn track(mut self, words: impl IntoIterator<Item = String>) -> Self {
+        self.positions = words.into_iter().map(|word| (word, Vec::new())).collect();
+        self
+    }
+
+    /// Count the next (already normalized) token of the text
+    pub fn push(&mut self, word: String, stopword: bool) {
+        self.text.push(&word);
+        self.segmenter.word(&word);
+        self.count(word, stopword);
+    }
+
+    /// Count the next normalized word, remembering the surface `form` it came from
+    pub fn push_form(&mut self, word: String, form: &str, stopword: bool) {
+        self.text.push(form);
+        self.segmenter.word(form);
+        if !stopword {
+            let forms = self.forms.entry(word.clone()).or_default();
+            match forms.get_mut(form) {
+                Some(count) => *count += 1,
+                None => {
+                    forms.insert(form.to_string(), 1);
+                }
+            }
+        }
+        self.count(word, stopword);
+    }
+
+    /// Record the text between two tokens, where the sentence and paragraph boundaries are
+    ///
+    /// `next` is the following token as written (its case matters),
+    /// `None` at the end of a chunk
+    pub fn push_gap(&mut self, gap: &str, next: Option<&str>) {
+        self.segmenter.gap(gap, next);
+    }
+
+    fn count(&mut self, word: String, stopword: bool) {
+        let position = self.tokens_seen;
+        self.tokens_seen += 1;
+        if !stopword {
+            record(&mut self.words, &word, self.total_words);
+            if let Some(positions) = self.positions.get_mut(&word) {
+                positions.push(self.total_words);
+            }
+            self.total_words += 1;
+            self.sequence.push(&word);
+        }
+
+        let max_n = self.ngrams.keys().next_back().copied().unwrap_or(0);
+        if max_n == 0 {
+            return;
+        }
+        self.window.push_back((word, stopword));
+        if self.window.len() > max_n {
+            self.window.pop_front();
+        }
+        for (&n, table) in self.ngrams.iter_mut() {
+            let Some(start) = self.window.len().checked_sub(n) else {
+                continue;
+            };
+            let gram = self.window.range(start..);
+            // filter n-grams containing stopwords
+            if gram.clone().any(|(_, stopword)| *stopword) {
+                continue;
+            }
+            let text = gram
+                .map(|(word, _)| word.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            record(table, &text, position + 1 - n);
+        }
+    }
+
+    /// Mark the end of a document, n-grams never span two documents
+    pub fn end_document(&mut self) {
+        self.window.clear();
+        self.sequence.end_document();
+        self.segmenter.end_document();
+    }
+
+    /// Add the counts of `other`, as if its text came right after this one
+    ///
+    /// Merging the tables of several documents in order gives the same
+    /// result as counting the documents one after the other
+    pub fn merge(&mut self, other: CountTable) {
+        let offset = self.tokens_seen;
+        if offset == 0 {
+            self.language = other.language;
+        } else if self.language != other.language {
+            self.language = None;
+        }
+        merge_table(&mut self.words, other.words, self.total_words);
+        for (word, positions) in other.positions {
+            let offset = self.total_words;
+            self.positions
+                .entry(word)
+                .or_default()
+                .extend(positions.into_iter().map(|position| position + offset));
+        }
+        for (n, table) in other.ngrams {
+            merge_table(self.ngrams.entry(n).or_default(), table, offset);
+        }
+        for (word, forms) in other.forms {
+            let into = self.forms.entry(word).or_default();
+            for (form, count) in forms {
+                *into.entry(form).or_default() += count;
+            }
+        }
+        self.sequence.merge(other.sequence);
+        self.text.merge(other.text);
+        self.segmenter.merge(other.segmenter);
+        self.total_words += other.total_words;
+        self.tokens_seen += other.tokens_seen;
+        self.end_document();
+    }
+
+    /// Counted (non stopword) tokens so far
+    pub fn total_words(&self) -> usize {
+        self.total_words
+    }
+}
+
+fn merge_table(into: &mut HashMap<String, Entry>, from: HashMap<String, Entry>, offset: usize) {
+    for (text, entry) in from {
+        into.entry(text)
+            .and_modify(|e| e.count += entry.count)
+            .or_insert(Entry {
+                count: entry.count,
+                first: entry.first + offset,
+            });
+    }
+}
+
+fn record(table: &mut HashMap<String, Entry>, text: &str, position: usize) {
+    match table.get_mut(text) {
+        Some(entry) => entry.count += 1,
+        None => {
+            table.insert(
+                text.to_string(),
+                Entry {
+                    count: 1,
+                    first: position,
+                },
+            );
+        }
+    }
+}
@@ -1,123 +1,174 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize)]
+use crate::{
+    counts::{CountTable, Entry},
+    diversity::Diversity,
+    error::{Error, Result},
+    language::Language,
+    laws::{GrowthPoint, vocabulary_growth},
+    readability::Readability,
+    sentence::Structure,
+    tokenizer::{SimpleTokenizer, Tokenizer, TokenizerKind},
+};
+
+/// The n-gram sizes computed when none are specified
+pub const DEFAULT_NGRAM_SIZES: [usize; 2] = [2, 3];
+
+/// Version of the JSON documents: the serialized [`WordProcessor`], [`crate::Comparison`]
+/// and [`crate::TfIdf`], bumped whenever their fields change
+pub const FORMAT_VERSION: u32 = 3;
+
+/// A set of stopwords excluded from the analysis
+#[derive(Clone, Deserialize)]
 pub struct WordFilter(HashSet<String>);
 impl WordFilter {
     pub fn contains(&self, s: &str) -> bool {
         self.0.contains(s)
     }
+
+    /// The embedded english stopword list
+    pub fn english() -> Self {
+        Language::English.stopwords().clone()
+    }
+
+    /// Parse a JSON array of words
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        Ok(serde_json::from_reader(BufReader::new(reader))?)
+    }
+
+    /// Parse the JSON array of words stored at `path`
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for WordFilter {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        Self(iter.into_iter().map(Into::into).collect())
+    }
 }
 
-#[derive(Serialize)]
+/// Word frequencies and statistics of a text (or corpus)
+///
+/// Word counts don't include stopwords, unless they were analyzed
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct WordProcessor {
+    /// Format version of the analysis ([`FORMAT_VERSION`]), missing (0) in files older than versioning
+    #[serde(default)]
+    pub version: u32,
+    /// Where and how the analysis was made, set by the command line tool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    /// Average length of the distinct words, in bytes
     pub avglen: f64,
+    /// Type-token ratio, distinct words / counted words (0-1)
     pub ttr: f64,
+    /// Counted words (tokens)
     pub total_words: usize,
+    /// Distinct words counted only once
     pub rare_words: usize,
+    /// Distinct words (types)
     pub unique_words: usize,
+    /// Length-robust lexical diversity measures
+    pub diversity: Diversity,
+    /// Sentence, syllable and letter counts with the readability scores
+    pub readability: Readability,
+    /// Sentence and paragraph statistics
+    pub structure: Structure,
+    /// Vocabulary size at geometrically spaced points of the text
+    pub vocabulary_growth: Vec<GrowthPoint>,
+    /// Every distinct word, most frequent first
     pub words: Vec<WordData>,
-    pub bigrams: Vec<WordData>,
-    pub trigrams: Vec<WordData>,
+    /// n-gram tables keyed by n, most frequent first
+    pub ngrams: BTreeMap<usize, Vec<WordData>>,
+    /// Indices among the counted words of the words tracked by [`crate::Analyzer::track_positions`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub positions: BTreeMap<String, Vec<usize>>,
+    /// Detected language, when language detection is on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
 }
 
 impl WordProcessor {
-    pub fn from_str(analyze_text: &str, filter: &Option<WordFilter>) -> Self {
-        let mut total_words = 0;
-        // closure to accumulate to a hashmap
-        let collect_to_hashmap = |mut acc: HashMap<_, _>, elem| {
-            acc.entry(elem).and_modify(|e| *e += 1).or_insert(1);
-            acc
-        };
-
-        let data = analyze_text
-            .split(|c: char| {
-                // common split particles
-                c.is_whitespace()
-                    || c == ','
-                    || c == '.'
-                    || c == '"'
-                    || c == '!'
-                    || c == '?'
-                    || c == '-'
-                    || c == '—'
-            })
-            .map(|word| word.to_lowercase())
-            .filter(|s| {
-                // only get the words that are alphabetic
-                let f = !s.is_empty() && s.chars().all(|c| c.is_alphabetic());
-                if let Some(filter) = filter.as_ref() {
-                    f && !filter.contains(s)
-                } else {
-                    f
-                }
-            })
-            .inspect(|_| {
-                // increment the word count
-                total_words += 1;
-            })
-            .collect::<Vec<_>>();
-
-        let split = analyze_text
-            .split(|c: char| {
-                c.is_whitespace()
-                    || c == ','
-                    || c == '.'
-                    || c == '"'
-                    || c == '!'
-                    || c == '?'
-                    || c == '-'
-                    || c == '—'
-            })
-            .map(|word| word.to_lowercase().chars().collect::<String>())
-            .filter(|s| !s.is_empty() && { s.chars().all(|c| c.is_alphabetic()) });
-        // tuple of (i, i+1)
-        let bigrams = split.clone().zip(split.clone().skip(1)).collect::<Vec<_>>();
-        let mut trigrams = bigrams
-            .clone()
-            .into_iter()
-            .zip(split.skip(2))
-            .filter(|((a, b), c)| {
-                // filter trigrams with more than 1 stopword
-                filter.is_none()
-                    || filter.as_ref().is_some_and(|filter| {
-                        !(filter.contains(a) || filter.contains(b) || filter.contains(c))
-                    })
-            })
-            .map(|((a, b), c)| format!("{a} {b} {c}"))
-            .fold(HashMap::new(), collect_to_hashmap)
-            .into_iter()
-            .map(|(text, count)| WordData { text, count })
-            .collect::<Vec<_>>();
-        let mut bigrams = bigrams
-            .iter()
-            .filter(|(a, b)| {
-                // filter bigrams with more than 1 stopword
-                filter.is_none()
-                    || filter
-                        .as_ref()
-                        .is_some_and(|filter| !(filter.contains(a) || filter.contains(b)))
-            })
-            .map(|(a, b)| format!("{a} {b}"))
-            .fold(HashMap::new(), collect_to_hashmap)
-            .into_iter()
-            .map(|(text, count)| WordData { text, count })
-            .collect::<Vec<_>>();
-        let data = data.into_iter().fold(HashMap::new(), collect_to_hashmap);
-        let mut words = data
+    /// Analyze `analyze_text` with the [`SimpleTokenizer`]
+    ///
+    /// # Errors
+    /// [`Error::EmptyInput`] if no words are left after filtering
+    pub fn from_str(
+        analyze_text: &str,
+        filter: &Option<WordFilter>,
+        tie_break: TieBreak,
+    ) -> Result<Self> {
+        Self::from_tokens(
+            SimpleTokenizer
+                .tokenize(analyze_text)
+                .into_iter()
+                .map(|(_, word)| word),
+            filter,
+            tie_break,
+            &DEFAULT_NGRAM_SIZES,
+        )
+    }
+
+    /// Analyze an already tokenized text
+    ///
+    /// # Errors
+    /// [`Error::EmptyInput`] if no words are left after filtering
+    pub fn from_tokens<'t>(
+        tokens: impl Iterator<Item = &'t str>,
+        filter: &Option<WordFilter>,
+        tie_break: TieBreak,
+        ngram_sizes: &[usize],
+    ) -> Result<Self> {
+        let mut counts = CountTable::new(ngram_sizes);
+        for word in tokens.map(|word| word.to_lowercase()) {
+            let stopword = filter.as_ref().is_some_and(|filter| filter.contains(&word));
+            counts.push(word, stopword);
+        }
+        Self::from_counts(counts, tie_break)
+    }
+
+    /// Build the statistics out of the collected counts
+    ///
+    /// # Errors
+    /// [`Error::EmptyInput`] if no words were counted
+    pub fn from_counts(counts: CountTable, tie_break: TieBreak) -> Result<Self> {
+        if counts.total_words() == 0 {
+            return Err(Error::EmptyInput);
+        }
+        let language = counts.language;
+        let total_words = counts.total_words;
+        let ngrams = counts
+            .ngrams
             .into_iter()
-            .map(|(text, count)| WordData { text, count })
-            .collect::<Vec<WordData>>();
-        words.sort_by(|a, b| b.count.cmp(&a.count));
-        bigrams.sort_by(|a, b| b.count.cmp(&a.count));
-        trigrams.sort_by(|a, b| b.count.cmp(&a.count));
+            .map(|(n, table)| (n, rank(table, &mut HashMap::new(), tie_break)))
+            .collect();
+        let vocabulary_growth = vocabulary_growth(
+            counts.words.values().map(|entry| entry.first).collect(),
+            total_words,
+        );
+        let mut forms = counts.forms;
+        let words = rank(counts.words, &mut forms, tie_break);
 
         let avglen =
             words.iter().map(|data| data.text.len()).sum::<usize>() as f64 / words.len() as f64;
         let ttr = words.len() as f64 / total_words as f64;
         let rare_words = words.iter().filter(|word| word.count == 1).count();
-        Self {
+        let diversity = Diversity::new(&words, total_words, &counts.sequence);
+        let readability = Readability::new(&counts.text, counts.segmenter.sentences());
+        let structure = Structure::new(counts.segmenter);
+        let positions = counts.positions.into_iter().collect();
+        Ok(Self {
+            version: FORMAT_VERSION,
+            metadata: None,
             //store the length for json purposes
             unique_words: words.len(),
             words,
@@ -125,9 +176,45 @@ impl WordProcessor {
             total_words,
             ttr,
             rare_words,
-            bigrams,
-            trigrams,
+            diversity,
+            readability,
+            structure,
+            vocabulary_growth,
+            ngrams,
+            positions,
+            language,
+        })
+    }
+
+    /// Load an analysis saved as JSON
+    ///
+    /// # Errors
+    /// [`Error::NotAnalysis`] if it is another JSON document (eg. a comparison),
+    /// [`Error::Version`] if it was saved by another version, [`Error::Json`] if it is malformed
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_reader(BufReader::new(reader))?;
+        // every analysis ever written has its words
+        if value.get("words").is_none() {
+            return Err(Error::NotAnalysis);
         }
+        // check the version first, an old file likely misses fields too
+        let version = value.get("version").and_then(serde_json::Value::as_u64);
+        match version {
+            Some(version) if version == u64::from(FORMAT_VERSION) => {
+                Ok(serde_json::from_value(value)?)
+            }
+            _ => Err(Error::Version(version.unwrap_or(0))),
+        }
+    }
+
+    /// Load the analysis saved as JSON at `path`
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// The n-gram table for `n`, empty if it wasn't computed
+    pub fn ngrams(&self, n: usize) -> &[WordData] {
+        self.ngrams.get(&n).map(Vec::as_slice).unwrap_or_default()
     }
     pub fn get_variation_string(&self) -> String {
         if self.ttr < 0.05 {
@@ -142,8 +229,148 @@ impl WordProcessor {
     }
 }
 
-#[derive(Deserialize, Serialize)]
+/// Where and how an analysis was made
+///
+/// Indexes store it with bincode, so no field may be skipped when serializing:
+/// a new field needs a [`FORMAT_VERSION`] (and [`crate::index::INDEX_VERSION`]) bump
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Metadata {
+    /// Version of wordfreq that made the analysis
+    pub tool_version: String,
+    /// When the analysis was made, as an RFC 3339 UTC timestamp
+    pub created: String,
+    /// The analyzed paths, `-` being the standard input
+    pub sources: Vec<String>,
+    pub tokenizer: TokenizerKind,
+    /// Pattern of the regex tokenizer, since format version 3
+    #[serde(default)]
+    pub token_pattern: Option<String>,
+    /// Stopword list: a built-in language, `auto` (detected per text) or the path of a custom list,
+    /// none when stopwords were counted
+    pub filter: Option<String>,
+    /// `stem`, or the path of the lemma dictionary, none when words were counted as written
+    pub normalizer: Option<String>,
+}
+
+impl Metadata {
+    /// Metadata of an analysis of `sources` made now, with the default tokenizer and no filter
+    pub fn new(sources: Vec<String>) -> Self {
+        Self {
+            tool_version: env!("CARGO_PKG_VERSION").into(),
+            created: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            sources,
+            ..Self::default()
+        }
+    }
+}
+
+/// How entries with the same count are ordered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum TieBreak {
+    /// Alphabetical order of the text
+    #[default]
+    Alphabetical,
+    /// Order in which the entries first appear in the text
+    FirstOccurrence,
+}
+
+// sort the counts (descending), then by the tie breaker
+fn rank(
+    counts: HashMap<String, Entry>,
+    forms: &mut HashMap<String, BTreeMap<String, usize>>,
+    tie_break: TieBreak,
+) -> Vec<WordData> {
+    let mut ranked = counts.into_iter().collect::<Vec<_>>();
+    ranked.sort_by(|(a, a_entry), (b, b_entry)| {
+        b_entry
+            .count
+            .cmp(&a_entry.count)
+            .then_with(|| match tie_break {
+                TieBreak::Alphabetical => a.cmp(b),
+                TieBreak::FirstOccurrence => a_entry.first.cmp(&b_entry.first),
+            })
+    });
+    ranked
+        .into_iter()
+        .map(|(text, Entry { count, .. })| {
+            let mut forms = forms.remove(&text).unwrap_or_default();
+            // nothing was merged into the word
+            if forms.len() == 1 && forms.contains_key(&text) {
+                forms.clear();
+            }
+            WordData { text, count, forms }
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
 pub struct WordData {
+    /// The word, or the n-gram words separated by spaces
     pub text: String,
+    /// Occurrences in the text
     pub count: usize,
+    /// Surface forms merged into this entry by stemming or lemmatization, with their counts
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub forms: BTreeMap<String, usize>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ties_are_ordered() {
+        let text = "pear apple fig apple pear fig";
+        let alpha = WordProcessor::from_str(text, &None, TieBreak::Alphabetical).unwrap();
+        let first = WordProcessor::from_str(text, &None, TieBreak::FirstOccurrence).unwrap();
+        let texts = |p: &WordProcessor| p.words.iter().map(|w| w.text.clone()).collect::<Vec<_>>();
+        assert_eq!(texts(&alpha), ["apple", "fig", "pear"]);
+        assert_eq!(texts(&first), ["pear", "apple", "fig"]);
+        assert_eq!(alpha.ngrams(2)[0].text, "apple fig");
+        assert_eq!(first.ngrams(2)[0].text, "pear apple");
+    }
+
+    #[test]
+    fn ngrams_of_any_size() {
+        let filter = Some(WordFilter::from_iter(["the"]));
+        let text = "a b c d e a b c d e the a b c d";
+        let processor =
+            WordProcessor::from_tokens(text.split(' '), &filter, TieBreak::Alphabetical, &[4, 5])
+                .unwrap();
+        assert!(processor.ngrams(2).is_empty());
+        assert_eq!(processor.ngrams(4)[0].text, "a b c d");
+        assert_eq!(processor.ngrams(4)[0].count, 3);
+        // "e the a b c" and friends are dropped
+        assert_eq!(
+            processor.ngrams(5).iter().map(|w| w.count).sum::<usize>(),
+            6
+        );
+    }
+
+    #[test]
+    fn saved_analysis_loads() {
+        let text = "The cat sat. The cat ran!\n\nA dog barked at the cat.";
+        let processor = WordProcessor::from_str(text, &None, TieBreak::Alphabetical).unwrap();
+        let json = serde_json::to_string(&processor).unwrap();
+        let loaded = WordProcessor::from_reader(json.as_bytes()).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+        assert!(matches!(
+            WordProcessor::from_str("   ", &None, TieBreak::Alphabetical),
+            Err(Error::EmptyInput)
+        ));
+
+        let old = json.replace(&format!("\"version\":{FORMAT_VERSION},"), "");
+        assert!(matches!(
+            WordProcessor::from_reader(old.as_bytes()),
+            Err(Error::Version(0))
+        ));
+
+        let comparison = crate::Comparison::new(vec!["text".into()], &[processor], 5);
+        let json = serde_json::to_string(&comparison).unwrap();
+        assert!(matches!(
+            WordProcessor::from_reader(json.as_bytes()),
+            Err(Error::NotAnalysis)
+        ));
+    }
 }
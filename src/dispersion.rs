@@ -0,0 +1,98 @@
+use serde::Serialize;
+
+/// How evenly a word is spread over a text cut into equal segments
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Dispersion {
+    pub word: String,
+    pub count: usize,
+    /// Occurrences in every segment
+    pub frequencies: Vec<usize>,
+    /// Segments the word occurs in
+    pub range: usize,
+    /// Juilland's D, from 0 (in one segment) to 1 (perfectly even)
+    pub juilland_d: f64,
+    /// Gries' DP, from 0 (perfectly even) to almost 1 (in one segment)
+    pub gries_dp: f64,
+}
+
+impl Dispersion {
+    /// Split the `total_words` counted words into `segments` equal parts,
+    /// `None` if the word never occurs or there are fewer than 2 segments
+    ///
+    /// `positions` are the indices of the word among the counted words
+    pub fn new(
+        word: impl Into<String>,
+        positions: &[usize],
+        total_words: usize,
+        segments: usize,
+    ) -> Option<Self> {
+        if positions.is_empty() || segments < 2 || total_words == 0 {
+            return None;
+        }
+        let mut frequencies = vec![0; segments];
+        for &position in positions {
+            frequencies[(position * segments / total_words).min(segments - 1)] += 1;
+        }
+        let count = positions.len() as f64;
+        let n = segments as f64;
+
+        let mean = count / n;
+        let std_dev = (frequencies
+            .iter()
+            .map(|&f| (f as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n)
+            .sqrt();
+        // segment i holds the words [i * total / n, (i + 1) * total / n)
+        let size = |i: usize| {
+            ((i + 1) * total_words / segments - i * total_words / segments) as f64
+                / total_words as f64
+        };
+        let gries_dp = frequencies
+            .iter()
+            .enumerate()
+            .map(|(i, &f)| (f as f64 / count - size(i)).abs())
+            .sum::<f64>()
+            / 2.0;
+
+        Some(Self {
+            word: word.into(),
+            count: positions.len(),
+            range: frequencies.iter().filter(|&&f| f > 0).count(),
+            juilland_d: 1.0 - std_dev / mean / (n - 1.0).sqrt(),
+            gries_dp,
+            frequencies,
+        })
+    }
+}
+
+/// Lexical barcode of `width` columns: `|` where the word occurs, `-` elsewhere
+pub fn barcode(positions: &[usize], total_words: usize, width: usize) -> String {
+    let mut columns = vec!['-'; width];
+    for &position in positions {
+        if let Some(column) = (position * width).checked_div(total_words) {
+            columns[column.min(width - 1)] = '|';
+        }
+    }
+    columns.into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn even_and_clumped() {
+        let even = Dispersion::new("even", &[0, 25, 50, 75], 100, 4).unwrap();
+        assert_eq!(even.range, 4);
+        assert!((even.juilland_d - 1.0).abs() < 1e-9);
+        assert!(even.gries_dp.abs() < 1e-9);
+
+        let clumped = Dispersion::new("clumped", &[0, 1, 2, 3], 100, 4).unwrap();
+        assert_eq!(clumped.frequencies, [4, 0, 0, 0]);
+        assert!(clumped.juilland_d.abs() < 1e-9);
+        assert!((clumped.gries_dp - 0.75).abs() < 1e-9);
+
+        assert_eq!(barcode(&[0, 99], 100, 10), "|--------|");
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::data::WordData;
+
+/// Words in every MATTR window
+pub const MATTR_WINDOW: usize = 100;
+/// TTR at which an MTLD segment ends
+pub const MTLD_THRESHOLD: f64 = 0.72;
+/// Words drawn in the HD-D sample
+pub const HDD_SAMPLE: usize = 42;
+
+/// Lexical diversity measures that, unlike the type-token ratio, barely depend on the text length
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Diversity {
+    /// Moving-average TTR over windows of [`MATTR_WINDOW`] words, the plain TTR for shorter texts
+    pub mattr: f64,
+    /// Measure of textual lexical diversity, the mean length of the segments
+    /// keeping their TTR above [`MTLD_THRESHOLD`] (forward pass only, so it can be streamed)
+    pub mtld: f64,
+    /// Expected TTR of a random sample of [`HDD_SAMPLE`] words (hypergeometric distribution)
+    pub hdd: f64,
+    /// Yule's K, the chance of drawing the same word twice scaled by 10 000 (lower is more diverse)
+    pub yules_k: f64,
+    /// Herdan's C, log(types) / log(tokens)
+    pub herdan_c: f64,
+    /// Guiraud's R, types / sqrt(tokens)
+    pub guiraud_r: f64,
+    /// Simpson's D, the chance of two words drawn without replacement being the same
+    pub simpson_d: f64,
+}
+
+impl Diversity {
+    pub(crate) fn new(words: &[WordData], total_words: usize, sequence: &SequenceStats) -> Self {
+        let n = total_words as f64;
+        let types = words.len() as f64;
+        let counts = || words.iter().map(|word| word.count as f64);
+
+        let sample = HDD_SAMPLE.min(total_words);
+        let hdd = counts()
+            .map(|count| {
+                // chance of the word missing from the sample
+                let missing = (0..sample)
+                    .map(|i| ((n - count - i as f64) / (n - i as f64)).max(0.0))
+                    .product::<f64>();
+                (1.0 - missing) / sample as f64
+            })
+            .sum();
+
+        Self {
+            mattr: sequence.mattr().unwrap_or(types / n),
+            mtld: sequence.mtld(),
+            hdd,
+            yules_k: 10_000.0 * (counts().map(|count| count * count).sum::<f64>() - n) / (n * n),
+            herdan_c: if total_words > 1 {
+                types.ln() / n.ln()
+            } else {
+                1.0
+            },
+            guiraud_r: types / n.sqrt(),
+            simpson_d: if total_words > 1 {
+                counts().map(|count| count * (count - 1.0)).sum::<f64>() / (n * (n - 1.0))
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// Running state of the measures that depend on the word order (MATTR and MTLD)
+///
+/// Neither the windows nor the segments span two documents,
+/// so merging the states of several documents gives the same result as streaming them
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SequenceStats {
+    window: VecDeque<String>,
+    window_types: HashMap<String, usize>,
+    // sum of the type counts of every full window
+    window_type_sum: usize,
+    windows: usize,
+    segment: HashSet<String>,
+    segment_tokens: usize,
+    mtld_tokens: usize,
+    factors: usize,
+    // partial factor of every document's last segment, summed in order at the end
+    partials: Vec<f64>,
+}
+
+impl SequenceStats {
+    pub(crate) fn push(&mut self, word: &str) {
+        self.window.push_back(word.to_string());
+        *self.window_types.entry(word.to_string()).or_default() += 1;
+        if self.window.len() > MATTR_WINDOW {
+            let old = self.window.pop_front().unwrap_or_default();
+            if let Some(count) = self.window_types.get_mut(&old) {
+                *count -= 1;
+                if *count == 0 {
+                    self.window_types.remove(&old);
+                }
+            }
+        }
+        if self.window.len() == MATTR_WINDOW {
+            self.window_type_sum += self.window_types.len();
+            self.windows += 1;
+        }
+
+        if !self.segment.contains(word) {
+            self.segment.insert(word.to_string());
+        }
+        self.segment_tokens += 1;
+        self.mtld_tokens += 1;
+        if (self.segment.len() as f64 / self.segment_tokens as f64) <= MTLD_THRESHOLD {
+            self.factors += 1;
+            self.segment.clear();
+            self.segment_tokens = 0;
+        }
+    }
+
+    pub(crate) fn end_document(&mut self) {
+        self.window.clear();
+        self.window_types.clear();
+        if self.segment_tokens > 0 {
+            self.partials.push(self.partial());
+        }
+        self.segment.clear();
+        self.segment_tokens = 0;
+    }
+
+    pub(crate) fn merge(&mut self, mut other: SequenceStats) {
+        self.end_document();
+        other.end_document();
+        self.window_type_sum += other.window_type_sum;
+        self.windows += other.windows;
+        self.mtld_tokens += other.mtld_tokens;
+        self.factors += other.factors;
+        self.partials.extend(other.partials);
+    }
+
+    // how far the open segment got towards the threshold
+    fn partial(&self) -> f64 {
+        let ttr = self.segment.len() as f64 / self.segment_tokens as f64;
+        (1.0 - ttr) / (1.0 - MTLD_THRESHOLD)
+    }
+
+    fn mattr(&self) -> Option<f64> {
+        (self.windows > 0)
+            .then(|| self.window_type_sum as f64 / (self.windows * MATTR_WINDOW) as f64)
+    }
+
+    fn mtld(&self) -> f64 {
+        let open = if self.segment_tokens > 0 {
+            self.partial()
+        } else {
+            0.0
+        };
+        let factors = self.factors as f64 + self.partials.iter().sum::<f64>() + open;
+        // no segment got anywhere near the threshold
+        if factors == 0.0 {
+            return self.mtld_tokens as f64;
+        }
+        self.mtld_tokens as f64 / factors
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Analyzer;
+
+    #[test]
+    fn known_values() {
+        let processor = Analyzer::new().analyze("a a b b c d").unwrap();
+        let diversity = processor.diversity;
+        // shorter than a window
+        assert_eq!(diversity.mattr, processor.ttr);
+        // "a a" and "b b" both drop to 0.5, "c d" never does
+        assert_eq!(diversity.mtld, 3.0);
+        // sample of the whole text
+        assert!((diversity.hdd - 4.0 / 6.0).abs() < 1e-9);
+        assert!((diversity.yules_k - 10_000.0 * 4.0 / 36.0).abs() < 1e-9);
+        assert!((diversity.simpson_d - 4.0 / 30.0).abs() < 1e-9);
+        assert!((diversity.guiraud_r - 4.0 / 6f64.sqrt()).abs() < 1e-9);
+    }
+}
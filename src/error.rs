@@ -0,0 +1,84 @@
+use std::fmt::Display;
+
+/// Everything that can go wrong while analyzing a text
+#[derive(Debug)]
+pub enum Error {
+    /// The input had no words left to analyze (empty text, or only stopwords)
+    EmptyInput,
+    /// Reading the text or a filter file failed
+    Io(std::io::Error),
+    /// A JSON document (eg. a stopword filter) could not be parsed
+    Json(serde_json::Error),
+    /// A tokenizer pattern is not a valid regular expression
+    Regex(regex::Error),
+    /// A token pattern was given to a tokenizer other than the regex one
+    UnusedPattern(crate::TokenizerKind),
+    /// An include or exclude glob is invalid
+    Glob(globset::Error),
+    /// A JSON document loaded as an analysis is something else, eg. a comparison
+    NotAnalysis,
+    /// A saved analysis was written by another format version (0 if it has none)
+    Version(u64),
+    /// A file is not a valid index, or was written by another version
+    Index(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::EmptyInput => write!(f, "there is no text to analyze"),
+            Error::Io(e) => write!(f, "io error: {e}"),
+            Error::Json(e) => write!(f, "invalid json: {e}"),
+            Error::Regex(e) => write!(f, "invalid pattern: {e}"),
+            Error::UnusedPattern(kind) => {
+                write!(
+                    f,
+                    "the {kind:?} tokenizer takes no pattern, only the regex one does"
+                )
+            }
+            Error::Glob(e) => write!(f, "invalid glob: {e}"),
+            Error::NotAnalysis => write!(f, "the JSON document is not a wordfreq analysis"),
+            Error::Version(version) => write!(
+                f,
+                "the analysis has format version {version}, expected {}",
+                crate::data::FORMAT_VERSION
+            ),
+            Error::Index(e) => write!(f, "invalid index: {e}"),
+        }
+    }
+}
+
+// the messages already include the wrapped errors, so they are not repeated as sources
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(e: regex::Error) -> Self {
+        Error::Regex(e)
+    }
+}
+
+impl From<globset::Error> for Error {
+    fn from(e: globset::Error) -> Self {
+        Error::Glob(e)
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(e: bincode::Error) -> Self {
+        Error::Index(e.to_string())
+    }
+}
@@ -0,0 +1,369 @@
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    compare::Comparison,
+    data::{WordData, WordProcessor},
+    tfidf::TfIdf,
+};
+
+/// How results are written to a file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// The whole result as pretty JSON
+    #[default]
+    Json,
+    /// Comma separated values, one file per table
+    Csv,
+    /// Tab separated values, one file per table
+    Tsv,
+    /// Markdown tables
+    Markdown,
+    /// Aligned plain text tables
+    Plain,
+    /// One JSON object per table row
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// The writer for this format, `None` for JSON which serializes the whole result
+    pub fn formatter(self) -> Option<Box<dyn Formatter>> {
+        match self {
+            OutputFormat::Json => None,
+            OutputFormat::Csv => Some(Box::new(Delimited(b','))),
+            OutputFormat::Tsv => Some(Box::new(Delimited(b'\t'))),
+            OutputFormat::Markdown => Some(Box::new(Markdown)),
+            OutputFormat::Plain => Some(Box::new(Plain)),
+            OutputFormat::Ndjson => Some(Box::new(Ndjson)),
+        }
+    }
+}
+
+/// A named table of results
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(name: impl Into<String>, columns: &[&str]) -> Self {
+        Self {
+            name: name.into(),
+            columns: columns.iter().map(|&column| column.into()).collect(),
+            rows: Vec::new(),
+        }
+    }
+}
+
+/// Results that can be laid out as tables
+pub trait Tabular {
+    fn tables(&self) -> Vec<Table>;
+}
+
+/// Writes tables in some format
+pub trait Formatter {
+    /// Whether a file holds a single table, several tables then go to several files
+    fn one_table_per_file(&self) -> bool {
+        false
+    }
+
+    fn write(&self, tables: &[Table], out: &mut dyn Write) -> io::Result<()>;
+}
+
+struct Delimited(u8);
+
+impl Formatter for Delimited {
+    fn one_table_per_file(&self) -> bool {
+        true
+    }
+
+    fn write(&self, tables: &[Table], out: &mut dyn Write) -> io::Result<()> {
+        let mut writer = csv::WriterBuilder::new().delimiter(self.0).from_writer(out);
+        for table in tables {
+            writer.write_record(&table.columns)?;
+            for row in &table.rows {
+                writer.write_record(row.iter().map(cell))?;
+            }
+        }
+        writer.flush()
+    }
+}
+
+struct Markdown;
+
+impl Formatter for Markdown {
+    fn write(&self, tables: &[Table], out: &mut dyn Write) -> io::Result<()> {
+        for (i, table) in tables.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "## {}\n", table.name)?;
+            writeln!(out, "| {} |", table.columns.join(" | "))?;
+            writeln!(out, "|{}", " --- |".repeat(table.columns.len()))?;
+            for row in &table.rows {
+                let row = row
+                    .iter()
+                    .map(|value| cell(value).replace('|', "\\|"))
+                    .collect::<Vec<_>>();
+                writeln!(out, "| {} |", row.join(" | "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Plain;
+
+impl Formatter for Plain {
+    fn write(&self, tables: &[Table], out: &mut dyn Write) -> io::Result<()> {
+        for (i, table) in tables.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{}:", table.name)?;
+            let rows = table
+                .rows
+                .iter()
+                .map(|row| row.iter().map(cell).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let widths = table
+                .columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    rows.iter()
+                        .filter_map(|row| row.get(i))
+                        .chain([column])
+                        .map(|text| text.chars().count())
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect::<Vec<_>>();
+            for row in [&table.columns].into_iter().chain(&rows) {
+                let line = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(text, &width)| format!("{text:<width$}"))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+                writeln!(out, "{}", line.trim_end())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Ndjson;
+
+impl Formatter for Ndjson {
+    fn write(&self, tables: &[Table], out: &mut dyn Write) -> io::Result<()> {
+        for table in tables {
+            let name = serde_json::to_string(&table.name)?;
+            for row in &table.rows {
+                // written by hand to keep the columns in order
+                write!(out, "{{\"table\":{name}")?;
+                for (column, value) in table.columns.iter().zip(row) {
+                    write!(out, ",{}:{value}", serde_json::to_string(column)?)?;
+                }
+                writeln!(out, "}}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// a value as table cell text
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+impl Tabular for WordProcessor {
+    fn tables(&self) -> Vec<Table> {
+        let mut statistics = Table::new("statistics", &["statistic", "value"]);
+        let readability = &self.readability;
+        let diversity = &self.diversity;
+        statistics.rows = [
+            ("total words", json!(self.total_words)),
+            ("unique words", json!(self.unique_words)),
+            ("rare words", json!(self.rare_words)),
+            ("type-token ratio", json!(self.ttr)),
+            ("average word length", json!(self.avglen)),
+            ("MATTR", json!(diversity.mattr)),
+            ("MTLD", json!(diversity.mtld)),
+            ("HD-D", json!(diversity.hdd)),
+            ("Yule's K", json!(diversity.yules_k)),
+            ("Herdan's C", json!(diversity.herdan_c)),
+            ("Guiraud's R", json!(diversity.guiraud_r)),
+            ("Simpson's D", json!(diversity.simpson_d)),
+            ("sentences", json!(readability.sentences)),
+            ("paragraphs", json!(self.structure.paragraphs)),
+            (
+                "Flesch reading ease",
+                json!(readability.flesch_reading_ease),
+            ),
+            (
+                "Flesch-Kincaid grade",
+                json!(readability.flesch_kincaid_grade),
+            ),
+            ("Gunning fog", json!(readability.gunning_fog)),
+            ("SMOG", json!(readability.smog)),
+            ("Coleman-Liau", json!(readability.coleman_liau)),
+        ]
+        .into_iter()
+        .map(|(name, value)| vec![name.into(), value])
+        .collect();
+
+        let ranked = |name: String, words: &[WordData]| {
+            let mut table = Table::new(name, &["rank", "text", "count"]);
+            table.rows = words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| vec![json!(i + 1), json!(word.text), json!(word.count)])
+                .collect();
+            table
+        };
+        let mut tables = vec![statistics, ranked("words".into(), &self.words)];
+        for (n, ngrams) in &self.ngrams {
+            tables.push(ranked(format!("{n}-grams"), ngrams));
+        }
+        tables
+    }
+}
+
+impl Tabular for Comparison {
+    fn tables(&self) -> Vec<Table> {
+        let names = self.names.iter().map(String::as_str);
+        let mut metrics = Table::new(
+            "metrics",
+            &["metric"]
+                .into_iter()
+                .chain(names.clone())
+                .collect::<Vec<_>>(),
+        );
+        metrics.rows = self
+            .metrics
+            .iter()
+            .map(|metric| {
+                [json!(metric.name)]
+                    .into_iter()
+                    .chain(metric.values.iter().map(|value| json!(value)))
+                    .collect()
+            })
+            .collect();
+
+        let mut shared = Table::new("shared", &["text"]);
+        shared.rows = self.shared.iter().map(|text| vec![json!(text)]).collect();
+
+        let mut exclusive = Table::new("exclusive", &["text", "source"]);
+        exclusive.rows = self
+            .exclusive
+            .iter()
+            .zip(&self.names)
+            .flat_map(|(words, name)| words.iter().map(move |text| vec![json!(text), json!(name)]))
+            .collect();
+
+        let counts = names.clone().map(|name| format!("{name} count"));
+        let rates = names.map(|name| format!("{name} per 10k"));
+        let mut differences = Table {
+            name: "differences".into(),
+            columns: ["text".to_string()]
+                .into_iter()
+                .chain(counts)
+                .chain(rates)
+                .chain(["spread".to_string()])
+                .collect(),
+            rows: Vec::new(),
+        };
+        differences.rows = self
+            .differences
+            .iter()
+            .map(|word| {
+                [json!(word.text)]
+                    .into_iter()
+                    .chain(word.counts.iter().map(|count| json!(count)))
+                    .chain(word.rates.iter().map(|rate| json!(rate)))
+                    .chain([json!(word.spread)])
+                    .collect()
+            })
+            .collect();
+
+        vec![metrics, shared, exclusive, differences]
+    }
+}
+
+impl Tabular for TfIdf {
+    fn tables(&self) -> Vec<Table> {
+        let mut table = Table::new(
+            "tfidf",
+            &[
+                "document",
+                "rank",
+                "text",
+                "count",
+                "document_frequency",
+                "score",
+            ],
+        );
+        table.rows = self
+            .documents
+            .iter()
+            .flat_map(|document| {
+                document.terms.iter().enumerate().map(|(i, term)| {
+                    vec![
+                        json!(document.name),
+                        json!(i + 1),
+                        json!(term.text),
+                        json!(term.count),
+                        json!(term.document_frequency),
+                        json!(term.score),
+                    ]
+                })
+            })
+            .collect();
+        vec![table]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formats_write_tables() {
+        let mut table = Table::new("words", &["text", "count"]);
+        table.rows = vec![vec![json!("a|b"), json!(2)], vec![json!("c,d"), json!(10)]];
+        let write = |format: OutputFormat| {
+            let mut out = Vec::new();
+            format
+                .formatter()
+                .unwrap()
+                .write(std::slice::from_ref(&table), &mut out)
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(write(OutputFormat::Csv), "text,count\na|b,2\n\"c,d\",10\n");
+        assert_eq!(write(OutputFormat::Tsv), "text\tcount\na|b\t2\nc,d\t10\n");
+        assert_eq!(
+            write(OutputFormat::Markdown),
+            "## words\n\n| text | count |\n| --- | --- |\n| a\\|b | 2 |\n| c,d | 10 |\n"
+        );
+        assert_eq!(
+            write(OutputFormat::Plain),
+            "words:\ntext  count\na|b   2\nc,d   10\n"
+        );
+        assert_eq!(
+            write(OutputFormat::Ndjson),
+            "{\"table\":\"words\",\"text\":\"a|b\",\"count\":2}\n{\"table\":\"words\",\"text\":\"c,d\",\"count\":10}\n"
+        );
+    }
+}
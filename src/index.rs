@@ -0,0 +1,687 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::Write,
+    ops::Range,
+    path::Path,
+};
+
+use bincode::Options;
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analyzer::Analyzer,
+    concordance::{Hit, MatchKind, word_regex},
+    data::{Metadata, WordData},
+    error::{Error, Result},
+};
+
+/// Version of the index layout, bumped whenever it changes
+pub const INDEX_VERSION: u32 = 1;
+/// First bytes of every index file
+const MAGIC: &[u8; 4] = b"WFQI";
+/// Marks a form that is never counted (a stopword)
+const NOT_COUNTED: u32 = u32::MAX;
+
+// the sections following the header, in file order
+const TEXT: usize = 1;
+// (start, length, form) of every token
+const TOKENS: usize = 2;
+// lowercase tokens, sorted
+const FORMS: usize = 3;
+// token indices of every form
+const FORM_TOKENS: usize = 5;
+// the word every form is counted as
+const FORM_WORDS: usize = 7;
+// counted (normalized) words, sorted
+const WORDS: usize = 8;
+// indices among the counted words of every word
+const WORD_POSITIONS: usize = 10;
+// the words from most to least frequent
+const WORD_RANKS: usize = 12;
+// (strings, counts) of every n-gram size, from most to least frequent
+const NGRAMS: usize = 13;
+
+// everything that isn't a table
+#[derive(Serialize, Deserialize)]
+struct Header {
+    metadata: Metadata,
+    documents: Vec<Document>,
+    total_words: usize,
+    ngram_sizes: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Document {
+    name: String,
+    // byte range in the text section
+    text: Range<usize>,
+    // index range in the tokens section
+    tokens: Range<usize>,
+}
+
+/// On-disk index of a corpus, for word and n-gram counts, concordances and dispersion
+/// without tokenizing the texts again
+///
+/// The file is memory-mapped and only the tables a query needs are read.
+/// It holds the texts, every token with its position and lowercase form,
+/// the counted words with their positions and the ranked n-gram tables.
+/// All numbers are little-endian `u32`s, so an index holds up to 4 GiB of text
+pub struct Index {
+    data: Box<dyn AsRef<[u8]> + Send + Sync>,
+    sections: Vec<Range<usize>>,
+    header: Header,
+}
+
+impl Index {
+    /// Index `documents` (name, text) as the analyzer counts them and write the index to `out`
+    ///
+    /// `metadata` must describe the analyzer's tokenizer, phrase queries are split with it
+    ///
+    /// # Errors
+    /// [`Error::EmptyInput`] if no words are counted, [`Error::Io`] if writing fails
+    pub fn write(
+        analyzer: &Analyzer,
+        documents: &[(String, String)],
+        metadata: Metadata,
+        out: &mut impl Write,
+    ) -> Result<()> {
+        let mut text = Vec::new();
+        // (start, length) of every token, and the tokens of every form
+        let mut tokens = Vec::new();
+        let mut forms = BTreeMap::<String, (Vec<u32>, Option<String>)>::new();
+        let mut words = BTreeMap::<String, Vec<u32>>::new();
+        let mut headers = Vec::new();
+        let mut counts = analyzer.count_table();
+        let mut counted = 0;
+        for (name, document) in documents {
+            let start = text.len();
+            let first_token = tokens.len();
+            for (offset, token, word) in analyzer.counted_tokens(document) {
+                let (postings, counted_as) = forms.entry(token.to_lowercase()).or_default();
+                postings.push(tokens.len() as u32);
+                tokens.push(((start + offset) as u32, token.len() as u32));
+                if let Some(word) = word {
+                    words.entry(word.clone()).or_default().push(counted);
+                    counted += 1;
+                    counted_as.get_or_insert(word);
+                }
+            }
+            text.extend_from_slice(document.as_bytes());
+            // counted on its own then merged, like every other multi-document analysis
+            let mut table = analyzer.count_table();
+            analyzer.count_into(document, &mut table);
+            counts.merge(table);
+            headers.push(Document {
+                name: name.clone(),
+                text: start..text.len(),
+                tokens: first_token..tokens.len(),
+            });
+        }
+        if u32::try_from(text.len()).is_err() {
+            return Err(Error::Index("more than 4 GiB of text".into()));
+        }
+        let processor = analyzer.finish(counts)?;
+
+        let form_ids = forms
+            .keys()
+            .enumerate()
+            .map(|(id, form)| (form.as_str(), id as u32))
+            .collect::<HashMap<_, _>>();
+        let word_ids = words
+            .keys()
+            .enumerate()
+            .map(|(id, word)| (word.as_str(), id as u32))
+            .collect::<HashMap<_, _>>();
+        let token_forms = {
+            let mut token_forms = vec![0; tokens.len()];
+            for (form, (postings, _)) in &forms {
+                for &token in postings {
+                    token_forms[token as usize] = form_ids[form.as_str()];
+                }
+            }
+            token_forms
+        };
+
+        let header = Header {
+            metadata,
+            documents: headers,
+            total_words: processor.total_words,
+            ngram_sizes: processor.ngrams.keys().copied().collect(),
+        };
+        let mut sections = vec![bincode::serialize(&header)?, text];
+        sections.push(u32s(
+            tokens
+                .iter()
+                .zip(&token_forms)
+                .flat_map(|(&(start, len), &form)| [start, len, form]),
+        ));
+        sections.extend(strings(forms.keys().map(String::as_str)));
+        sections.extend(lists(
+            forms.values().map(|(postings, _)| postings.as_slice()),
+        ));
+        sections.push(u32s(forms.values().map(|(_, word)| {
+            word.as_deref().map_or(NOT_COUNTED, |word| word_ids[word])
+        })));
+        sections.extend(strings(words.keys().map(String::as_str)));
+        sections.extend(lists(words.values().map(Vec::as_slice)));
+        sections.push(u32s(
+            processor
+                .words
+                .iter()
+                .map(|word| word_ids[word.text.as_str()]),
+        ));
+        for ngrams in processor.ngrams.values() {
+            sections.extend(strings(ngrams.iter().map(|ngram| ngram.text.as_str())));
+            sections.push(u32s(ngrams.iter().map(|ngram| ngram.count as u32)));
+        }
+
+        // magic, version, section count, then the (offset, length) of every section
+        let mut offset = (12 + 16 * sections.len()) as u64;
+        out.write_all(MAGIC)?;
+        out.write_all(&INDEX_VERSION.to_le_bytes())?;
+        out.write_all(&(sections.len() as u32).to_le_bytes())?;
+        for section in &sections {
+            out.write_all(&offset.to_le_bytes())?;
+            out.write_all(&(section.len() as u64).to_le_bytes())?;
+            offset += section.len() as u64;
+        }
+        for section in &sections {
+            out.write_all(section)?;
+        }
+        Ok(())
+    }
+
+    /// Memory-map the index at `path`
+    ///
+    /// # Errors
+    /// [`Error::Io`] if it can't be read, [`Error::Index`] if it isn't an index of this version
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the map is only read, the file must not be changed while the index is open
+        let map = unsafe { Mmap::map(&file)? };
+        Self::from_bytes(map)
+    }
+
+    /// Read an index from its bytes
+    pub fn from_bytes(data: impl AsRef<[u8]> + Send + Sync + 'static) -> Result<Self> {
+        let bytes = data.as_ref();
+        if bytes.get(..4) != Some(MAGIC) {
+            return Err(Error::Index("not a wordfreq index".into()));
+        }
+        let numbers = U32s(bytes.get(4..12).unwrap_or_default());
+        if numbers.get(0) != INDEX_VERSION {
+            return Err(Error::Index(format!(
+                "the index has version {}, expected {INDEX_VERSION}",
+                numbers.get(0)
+            )));
+        }
+        let count = numbers.get(1) as usize;
+        let table = bytes
+            .get(12..12 + 16 * count)
+            .ok_or_else(|| Error::Index("truncated section table".into()))?;
+        let sections = table
+            .chunks_exact(16)
+            .map(|entry| {
+                let start = u64::from_le_bytes(entry[..8].try_into().unwrap_or_default()) as usize;
+                let len = u64::from_le_bytes(entry[8..].try_into().unwrap_or_default()) as usize;
+                match start.checked_add(len) {
+                    Some(end) if end <= bytes.len() => Ok(start..end),
+                    _ => Err(Error::Index("truncated section".into())),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if sections.len() < NGRAMS {
+            return Err(Error::Index("missing sections".into()));
+        }
+        // the lengths inside the header can't make it read (or allocate) past its section
+        let header: Header = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(sections[0].len() as u64)
+            .deserialize(&bytes[sections[0].clone()])?;
+        if sections.len() < NGRAMS + 3 * header.ngram_sizes.len() {
+            return Err(Error::Index("missing n-gram sections".into()));
+        }
+        Ok(Self {
+            data: Box::new(data),
+            sections,
+            header,
+        })
+    }
+
+    /// How the indexed texts were analyzed
+    pub fn metadata(&self) -> &Metadata {
+        &self.header.metadata
+    }
+
+    /// Names of the indexed documents
+    pub fn documents(&self) -> impl Iterator<Item = &str> {
+        self.header
+            .documents
+            .iter()
+            .map(|document| document.name.as_str())
+    }
+
+    /// Counted words
+    pub fn total_words(&self) -> usize {
+        self.header.total_words
+    }
+
+    /// Distinct counted words
+    pub fn unique_words(&self) -> usize {
+        self.u32s(WORD_RANKS).len()
+    }
+
+    /// The `limit` most frequent words, ordered like [`crate::WordProcessor::words`]
+    pub fn top_words(&self, limit: usize) -> Vec<WordData> {
+        let ranks = self.u32s(WORD_RANKS);
+        let words = self.strings(WORDS);
+        let positions = self.lists(WORD_POSITIONS);
+        (0..ranks.len().min(limit))
+            .map(|rank| {
+                let id = ranks.get(rank) as usize;
+                WordData {
+                    text: words.get(id).into_owned(),
+                    count: positions.get(id).len(),
+                    forms: BTreeMap::new(),
+                }
+            })
+            .collect()
+    }
+
+    /// The `limit` most frequent n-grams of size `n`, empty if they weren't indexed
+    pub fn top_ngrams(&self, n: usize, limit: usize) -> Vec<WordData> {
+        let Some(i) = self.header.ngram_sizes.iter().position(|&size| size == n) else {
+            return Vec::new();
+        };
+        let texts = self.strings(NGRAMS + 3 * i);
+        let counts = self.u32s(NGRAMS + 3 * i + 2);
+        (0..counts.len().min(limit))
+            .map(|rank| WordData {
+                text: texts.get(rank).into_owned(),
+                count: counts.get(rank) as usize,
+                forms: BTreeMap::new(),
+            })
+            .collect()
+    }
+
+    /// Indices among the counted words of `word`, looked up as written then as counted,
+    /// see [`crate::WordProcessor::positions`]
+    pub fn positions(&self, word: &str) -> Vec<usize> {
+        let word = word.to_lowercase();
+        let id = match self.strings(FORMS).find(&word) {
+            Some(form) => Some(self.u32s(FORM_WORDS).get(form)).filter(|&id| id != NOT_COUNTED),
+            None => self.strings(WORDS).find(&word).map(|id| id as u32),
+        };
+        id.map(|id| {
+            let positions = self.lists(WORD_POSITIONS).get(id as usize);
+            (0..positions.len())
+                .map(|i| positions.get(i) as usize)
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+
+    /// Keyword in context search, the hits of every document in order of appearance
+    ///
+    /// The hits are the same as [`crate::Concordance::search`] on the texts
+    ///
+    /// # Errors
+    /// [`Error::Regex`] if a regex query doesn't compile, [`Error::Index`] if the index is damaged
+    pub fn concordance(
+        &self,
+        query: &str,
+        kind: MatchKind,
+        (left, right): (usize, usize),
+    ) -> Result<Vec<Vec<Hit>>> {
+        let forms = self.strings(FORMS);
+        let lowercase = query.to_lowercase();
+        // the first token of every match, with the number of matched tokens
+        let (starts, len) = match kind {
+            MatchKind::Exact => (self.tokens_of(forms.find(&lowercase)), 1),
+            MatchKind::Prefix => {
+                let start = forms.partition_point(|form| form < lowercase.as_str());
+                let end =
+                    start + forms.partition_point_from(start, |form| form.starts_with(&lowercase));
+                (self.tokens_of(start..end), 1)
+            }
+            MatchKind::Regex => {
+                let regex = word_regex(query)?;
+                let ids = (0..forms.len()).filter(|&id| regex.is_match(&forms.get(id)));
+                (self.tokens_of(ids), 1)
+            }
+            MatchKind::Phrase => {
+                let metadata = self.metadata();
+                let tokenizer = metadata
+                    .tokenizer
+                    .build(metadata.token_pattern.as_deref())?;
+                // a word that was never seen can't match
+                let phrase = tokenizer
+                    .tokenize(&lowercase)
+                    .into_iter()
+                    .map(|(_, word)| forms.find(word))
+                    .collect::<Option<Vec<_>>>()
+                    .unwrap_or_default();
+                let tokens = self.u32s(TOKENS);
+                let mut starts = Vec::new();
+                for start in self.tokens_of(phrase.first().copied()) {
+                    let document = self.document(start)?;
+                    let matches = start + phrase.len() <= document.tokens.end
+                        && phrase
+                            .iter()
+                            .enumerate()
+                            .skip(1)
+                            .all(|(k, &id)| tokens.get((start + k) * 3 + 2) as usize == id);
+                    if matches {
+                        starts.push(start);
+                    }
+                }
+                (starts, phrase.len())
+            }
+        };
+        self.hits(starts, len, (left, right))
+    }
+
+    // the tokens of the given forms, in order
+    fn tokens_of(&self, ids: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        let form_tokens = self.lists(FORM_TOKENS);
+        let mut tokens = ids
+            .into_iter()
+            .flat_map(|id| {
+                let postings = form_tokens.get(id);
+                (0..postings.len()).map(move |i| postings.get(i) as usize)
+            })
+            .collect::<Vec<_>>();
+        tokens.sort_unstable();
+        tokens
+    }
+
+    fn document_of(&self, token: usize) -> usize {
+        self.header
+            .documents
+            .partition_point(|document| document.tokens.end <= token)
+    }
+
+    // the document holding `token`
+    fn document(&self, token: usize) -> Result<&Document> {
+        self.header
+            .documents
+            .get(self.document_of(token))
+            .ok_or_else(|| damaged("a token past the last document"))
+    }
+
+    // the hits of `len` tokens starting at every one of `starts`, grouped by document
+    fn hits(
+        &self,
+        starts: Vec<usize>,
+        len: usize,
+        (left, right): (usize, usize),
+    ) -> Result<Vec<Vec<Hit>>> {
+        let tokens = self.u32s(TOKENS);
+        let text = self.bytes(TEXT);
+        let span = |token: usize| {
+            let start = tokens.get(token * 3) as usize;
+            start..start + tokens.get(token * 3 + 1) as usize
+        };
+        let mut hits = self
+            .header
+            .documents
+            .iter()
+            .map(|_| Vec::new())
+            .collect::<Vec<_>>();
+        for first in starts {
+            let document = self.document(first)?;
+            let last = first + len.max(1) - 1;
+            let left_token = first.saturating_sub(left).max(document.tokens.start);
+            let right_token = (last + right).min(document.tokens.end.saturating_sub(1));
+            let window = span(left_token).start..span(right_token).end.max(span(last).end);
+            // the context only, tokens of a sound index start and end on char boundaries
+            let context = text
+                .get(window.clone())
+                .and_then(|context| std::str::from_utf8(context).ok())
+                .ok_or_else(|| damaged("a token outside the text"))?;
+            let relative = |offset: usize| {
+                offset
+                    .checked_sub(window.start)
+                    .filter(|&offset| context.is_char_boundary(offset))
+                    .ok_or_else(|| damaged("a token outside its context"))
+            };
+            let offsets = (
+                0,
+                relative(span(first).start)?,
+                relative(span(last).end)?,
+                relative(window.end)?,
+            );
+            if offsets.1 > offsets.2 {
+                return Err(damaged("a token ending before it starts"));
+            }
+            let in_document = |offset: usize| {
+                offset
+                    .checked_sub(document.text.start)
+                    .ok_or_else(|| damaged("a token outside its document"))
+            };
+            let position = first
+                .checked_sub(document.tokens.start)
+                .ok_or_else(|| damaged("a token outside its document"))?;
+            let mut hit = Hit::new(context, offsets, position);
+            hit.start = in_document(span(first).start)?;
+            hit.end = in_document(span(last).end)?;
+            hits[self.document_of(first)].push(hit);
+        }
+        Ok(hits)
+    }
+
+    fn bytes(&self, section: usize) -> &[u8] {
+        &(*self.data).as_ref()[self.sections[section].clone()]
+    }
+
+    fn u32s(&self, section: usize) -> U32s<'_> {
+        U32s(self.bytes(section))
+    }
+
+    fn strings(&self, section: usize) -> Strings<'_> {
+        Strings {
+            offsets: self.u32s(section),
+            bytes: self.bytes(section + 1),
+        }
+    }
+
+    fn lists(&self, section: usize) -> Lists<'_> {
+        Lists {
+            offsets: self.u32s(section),
+            values: self.u32s(section + 1),
+        }
+    }
+}
+
+// little-endian u32s, read in place
+#[derive(Clone, Copy)]
+struct U32s<'a>(&'a [u8]);
+
+impl<'a> U32s<'a> {
+    fn len(self) -> usize {
+        self.0.len() / 4
+    }
+
+    // 0 past the end, so a damaged index gives wrong answers instead of panics
+    fn get(self, i: usize) -> u32 {
+        self.0.get(i * 4..i * 4 + 4).map_or(0, |bytes| {
+            u32::from_le_bytes(bytes.try_into().unwrap_or_default())
+        })
+    }
+
+    fn slice(self, range: Range<usize>) -> U32s<'a> {
+        U32s(
+            self.0
+                .get(range.start * 4..range.end * 4)
+                .unwrap_or_default(),
+        )
+    }
+}
+
+// the strings of a blob, `offsets` has one more entry than there are strings
+struct Strings<'a> {
+    offsets: U32s<'a>,
+    bytes: &'a [u8],
+}
+
+impl<'a> Strings<'a> {
+    fn len(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    fn get(&self, i: usize) -> Cow<'a, str> {
+        let range = self.offsets.get(i) as usize..self.offsets.get(i + 1) as usize;
+        String::from_utf8_lossy(self.bytes.get(range).unwrap_or_default())
+    }
+
+    // index of the first string (from `from`) for which `pred` is false, the strings are sorted
+    fn partition_point_from(&self, from: usize, pred: impl Fn(&str) -> bool) -> usize {
+        let (mut low, mut high) = (from, self.len());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if pred(&self.get(middle)) {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        low - from
+    }
+
+    fn partition_point(&self, pred: impl Fn(&str) -> bool) -> usize {
+        self.partition_point_from(0, pred)
+    }
+
+    // binary search of a sorted table
+    fn find(&self, value: &str) -> Option<usize> {
+        let i = self.partition_point(|string| string < value);
+        (i < self.len() && self.get(i) == value).then_some(i)
+    }
+}
+
+// lists of u32s, `offsets` has one more entry than there are lists
+struct Lists<'a> {
+    offsets: U32s<'a>,
+    values: U32s<'a>,
+}
+
+impl<'a> Lists<'a> {
+    fn get(&self, i: usize) -> U32s<'a> {
+        self.values
+            .slice(self.offsets.get(i) as usize..self.offsets.get(i + 1) as usize)
+    }
+}
+
+fn damaged(what: &str) -> Error {
+    Error::Index(format!("damaged index, {what}"))
+}
+
+fn u32s(values: impl IntoIterator<Item = u32>) -> Vec<u8> {
+    values.into_iter().flat_map(u32::to_le_bytes).collect()
+}
+
+// (offsets, bytes) sections of a string table
+fn strings<'s>(strings: impl IntoIterator<Item = &'s str>) -> [Vec<u8>; 2] {
+    let mut offsets = vec![0];
+    let mut bytes = Vec::new();
+    for string in strings {
+        bytes.extend_from_slice(string.as_bytes());
+        offsets.push(bytes.len() as u32);
+    }
+    [u32s(offsets), bytes]
+}
+
+// (offsets, values) sections of a table of lists
+fn lists<'l>(lists: impl IntoIterator<Item = &'l [u32]>) -> [Vec<u8>; 2] {
+    let mut offsets = vec![0];
+    let mut values = Vec::new();
+    for list in lists {
+        values.extend_from_slice(list);
+        offsets.push(values.len() as u32);
+    }
+    [u32s(offsets), u32s(values)]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Concordance, WordFilter};
+
+    #[test]
+    fn queries_match_the_texts() {
+        let documents = [
+            (
+                "a".to_string(),
+                "The cat sat on the mat. The Cat ran off!".to_string(),
+            ),
+            (
+                "b".to_string(),
+                "A catalog of cats, the cat said.\n\nCat naps.".to_string(),
+            ),
+        ];
+        let analyzer = Analyzer::new()
+            .filter(WordFilter::from_iter(["the", "a", "of", "on"]))
+            .track_positions(["cat"]);
+        let mut bytes = Vec::new();
+        Index::write(&analyzer, &documents, Metadata::default(), &mut bytes).unwrap();
+        let index = Index::from_bytes(bytes).unwrap();
+
+        for (query, kind) in [
+            ("cat", MatchKind::Exact),
+            ("cat", MatchKind::Prefix),
+            ("ca.s?", MatchKind::Regex),
+            ("the cat", MatchKind::Phrase),
+        ] {
+            let hits = index.concordance(query, kind, (2, 1)).unwrap();
+            let concordance = Concordance::new(query, kind).unwrap().context(2, 1);
+            for ((_, text), hits) in documents.iter().zip(hits) {
+                assert_eq!(hits, concordance.search(text, &analyzer), "{query}");
+            }
+        }
+
+        let texts = documents
+            .iter()
+            .map(|(_, text)| text.as_str())
+            .collect::<Vec<_>>();
+        let processor = analyzer.analyze(&texts.join("\n\n")).unwrap();
+        assert_eq!(index.total_words(), processor.total_words);
+        assert_eq!(index.top_words(3)[0].text, processor.words[0].text);
+        assert_eq!(index.top_ngrams(2, 1)[0].text, processor.ngrams(2)[0].text);
+        assert_eq!(index.positions("CAT"), processor.positions["cat"]);
+        assert!(index.positions("the").is_empty());
+
+        // damaged files give errors, not panics
+        assert!(Index::from_bytes(bytes_of(&index)[..200].to_vec()).is_err());
+        let damaged = |section: usize| {
+            let mut bytes = bytes_of(&index).to_vec();
+            bytes[index.sections[section].clone()].fill(0xFF);
+            Index::from_bytes(bytes)
+        };
+        // a huge length prefix in the header
+        assert!(damaged(0).is_err());
+        for section in [TOKENS, FORM_TOKENS + 1] {
+            let index = damaged(section).unwrap();
+            assert!(index.concordance("cat", MatchKind::Exact, (2, 1)).is_err());
+        }
+        // any damaged section, every query
+        for section in 1..index.sections.len() {
+            let Ok(index) = damaged(section) else {
+                continue;
+            };
+            for kind in [MatchKind::Exact, MatchKind::Prefix, MatchKind::Phrase] {
+                let _ = index.concordance("the cat", kind, (2, 1));
+            }
+            index.top_words(3);
+            index.top_ngrams(2, 3);
+            index.positions("cat");
+        }
+    }
+
+    fn bytes_of(index: &Index) -> &[u8] {
+        (*index.data).as_ref()
+    }
+}
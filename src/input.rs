@@ -0,0 +1,142 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use walkdir::WalkDir;
+
+use crate::error::Result;
+
+/// Where a text is read from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// The standard input, given as `-` on the command line
+    Stdin,
+    Path(PathBuf),
+}
+
+impl Source {
+    /// Open the source for reading
+    ///
+    /// The error names the path, sources are read in parallel and nothing else knows which failed
+    pub fn open(&self) -> Result<Box<dyn BufRead>> {
+        Ok(match self {
+            Source::Stdin => Box::new(io::stdin().lock()),
+            Source::Path(path) => {
+                let file = File::open(path).map_err(|e| self.failed("open", e))?;
+                Box::new(BufReader::new(file))
+            }
+        })
+    }
+
+    // the io error, naming this source
+    pub(crate) fn failed(&self, action: &str, e: io::Error) -> io::Error {
+        io::Error::new(e.kind(), format!("could not {action} {self}: {e}"))
+    }
+
+    /// Read the whole text
+    pub fn read_to_string(&self) -> Result<String> {
+        let mut text = String::new();
+        self.open()?.read_to_string(&mut text)?;
+        Ok(text)
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Stdin => write!(f, "<stdin>"),
+            Source::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Expands command line paths into the list of sources to analyze
+///
+/// Files are used as given, directories are walked and their files are
+/// filtered by the include and exclude globs (matched against the path
+/// relative to the directory)
+#[derive(Default)]
+pub struct SourceWalker {
+    recursive: bool,
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl SourceWalker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to descend into subdirectories
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Only keep directory entries matching one of `patterns`
+    pub fn include<S: AsRef<str>>(mut self, patterns: &[S]) -> Result<Self> {
+        self.include = glob_set(patterns)?;
+        Ok(self)
+    }
+
+    /// Skip directory entries matching one of `patterns`
+    pub fn exclude<S: AsRef<str>>(mut self, patterns: &[S]) -> Result<Self> {
+        self.exclude = glob_set(patterns)?;
+        Ok(self)
+    }
+
+    /// The sources behind `paths`, `-` standing for the standard input
+    pub fn collect(&self, paths: &[PathBuf]) -> Result<Vec<Source>> {
+        let mut sources = Vec::new();
+        for path in paths {
+            if path == Path::new("-") {
+                sources.push(Source::Stdin);
+            } else if path.is_dir() {
+                self.walk(path, &mut sources)?;
+            } else {
+                sources.push(Source::Path(path.clone()));
+            }
+        }
+        Ok(sources)
+    }
+
+    fn walk(&self, dir: &Path, sources: &mut Vec<Source>) -> Result<()> {
+        let depth = if self.recursive { usize::MAX } else { 1 };
+        // sorted so the sources (and first occurrences) are always in the same order
+        for entry in WalkDir::new(dir).max_depth(depth).sort_by_file_name() {
+            let entry = entry.map_err(io::Error::from)?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+            let included = self
+                .include
+                .as_ref()
+                .is_none_or(|include| include.is_match(relative));
+            let excluded = self
+                .exclude
+                .as_ref()
+                .is_some_and(|exclude| exclude.is_match(relative));
+            if included && !excluded {
+                sources.push(Source::Path(entry.into_path()));
+            }
+        }
+        Ok(())
+    }
+}
+
+// no patterns means no filtering
+fn glob_set<S: AsRef<str>>(patterns: &[S]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern.as_ref())?);
+    }
+    Ok(Some(builder.build()?))
+}
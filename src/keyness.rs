@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data::WordProcessor;
+
+/// Statistic the keywords are ranked by
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeynessMeasure {
+    /// Log-likelihood (G²)
+    #[default]
+    LogLikelihood,
+    /// Pearson's chi-square
+    ChiSquare,
+    /// Difference of the normalized frequencies, in percent
+    PercentDiff,
+}
+
+/// Significance level of the log-likelihood test (1 degree of freedom)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Significance {
+    #[value(name = "0.05")]
+    #[serde(rename = "0.05")]
+    P05,
+    #[default]
+    #[value(name = "0.01")]
+    #[serde(rename = "0.01")]
+    P01,
+    #[value(name = "0.001")]
+    #[serde(rename = "0.001")]
+    P001,
+    #[value(name = "0.0001")]
+    #[serde(rename = "0.0001")]
+    P0001,
+    /// Keep every word
+    #[value(name = "none")]
+    #[serde(rename = "none")]
+    None,
+}
+
+impl Significance {
+    /// Minimum log-likelihood for a word to be significant
+    pub fn critical_value(self) -> f64 {
+        match self {
+            Significance::P05 => 3.84,
+            Significance::P01 => 6.63,
+            Significance::P001 => 10.83,
+            Significance::P0001 => 15.13,
+            Significance::None => 0.0,
+        }
+    }
+}
+
+/// Keyness scores of one word
+#[derive(Serialize)]
+pub struct Keyword {
+    pub text: String,
+    pub target_count: usize,
+    pub reference_count: usize,
+    pub log_likelihood: f64,
+    pub chi_square: f64,
+    /// `null` in JSON (infinite) when the word is missing from the reference
+    pub percent_diff: f64,
+}
+
+impl Keyword {
+    fn score(&self, measure: KeynessMeasure) -> f64 {
+        match measure {
+            KeynessMeasure::LogLikelihood => self.log_likelihood,
+            KeynessMeasure::ChiSquare => self.chi_square,
+            // rank by the size of the difference on both sides
+            KeynessMeasure::PercentDiff => self.percent_diff.abs(),
+        }
+    }
+}
+
+/// Words significantly over-represented in a target text compared to a reference
+#[derive(Serialize)]
+pub struct Keyness {
+    pub measure: KeynessMeasure,
+    pub significance: Significance,
+    /// Over-represented in the target (positive keywords)
+    pub target: Vec<Keyword>,
+    /// Over-represented in the reference (negative keywords)
+    pub reference: Vec<Keyword>,
+}
+
+impl Keyness {
+    /// Score every word of both texts, keeping the `limit` best significant ones on each side
+    pub fn new(
+        target: &WordProcessor,
+        reference: &WordProcessor,
+        measure: KeynessMeasure,
+        significance: Significance,
+        limit: usize,
+    ) -> Self {
+        // word -> (target count, reference count)
+        let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for word in &target.words {
+            counts.entry(&word.text).or_default().0 = word.count;
+        }
+        for word in &reference.words {
+            counts.entry(&word.text).or_default().1 = word.count;
+        }
+
+        let c = target.total_words as f64;
+        let d = reference.total_words as f64;
+        let (mut over, mut under): (Vec<_>, Vec<_>) = counts
+            .into_iter()
+            .map(|(text, (target_count, reference_count))| {
+                let (a, b) = (target_count as f64, reference_count as f64);
+                Keyword {
+                    text: text.into(),
+                    target_count,
+                    reference_count,
+                    log_likelihood: log_likelihood(a, b, c, d),
+                    chi_square: chi_square(a, b, c, d),
+                    percent_diff: percent_diff(a, b, c, d),
+                }
+            })
+            .filter(|word| word.log_likelihood >= significance.critical_value())
+            .partition(|word| word.target_count as f64 / c >= word.reference_count as f64 / d);
+
+        for side in [&mut over, &mut under] {
+            // stable sort, ties stay alphabetical
+            side.sort_by(|a, b| b.score(measure).total_cmp(&a.score(measure)));
+            side.truncate(limit);
+        }
+        Self {
+            measure,
+            significance,
+            target: over,
+            reference: under,
+        }
+    }
+}
+
+/// Log-likelihood (G²) of a word seen `a` times in `c` words and `b` times in `d` words
+pub fn log_likelihood(a: f64, b: f64, c: f64, d: f64) -> f64 {
+    let e1 = c * (a + b) / (c + d);
+    let e2 = d * (a + b) / (c + d);
+    // 0 * ln(0) is taken as 0
+    let term = |o: f64, e: f64| if o > 0.0 { o * (o / e).ln() } else { 0.0 };
+    2.0 * (term(a, e1) + term(b, e2))
+}
+
+/// Pearson's chi-square of the 2x2 contingency table
+pub fn chi_square(a: f64, b: f64, c: f64, d: f64) -> f64 {
+    let n = c + d;
+    let denominator = (a + b) * (n - a - b) * c * d;
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    n * (a * (d - b) - b * (c - a)).powi(2) / denominator
+}
+
+/// %DIFF of the normalized frequencies, infinite if the word is missing from the reference
+pub fn percent_diff(a: f64, b: f64, c: f64, d: f64) -> f64 {
+    let (target, reference) = (a / c, b / d);
+    if reference == 0.0 {
+        return f64::INFINITY;
+    }
+    100.0 * (target - reference) / reference
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn known_values() {
+        // 10 in 1000 words vs 10 in 10000 words
+        assert!((log_likelihood(10.0, 10.0, 1000.0, 10000.0) - 22.14).abs() < 0.01);
+        assert!((chi_square(10.0, 10.0, 1000.0, 10000.0) - 40.57).abs() < 0.01);
+        assert!((percent_diff(10.0, 10.0, 1000.0, 10000.0) - 900.0).abs() < 1e-9);
+        // same relative frequency
+        assert!(log_likelihood(5.0, 50.0, 100.0, 1000.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::OnceLock;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{data::WordFilter, normalize::Algorithm};
+
+/// Languages with a built-in stopword list
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    clap::ValueEnum,
+)]
+pub enum Language {
+    #[serde(rename = "en")]
+    #[value(name = "en")]
+    English,
+    #[serde(rename = "ro")]
+    #[value(name = "ro")]
+    Romanian,
+    #[serde(rename = "fr")]
+    #[value(name = "fr")]
+    French,
+    #[serde(rename = "de")]
+    #[value(name = "de")]
+    German,
+    #[serde(rename = "es")]
+    #[value(name = "es")]
+    Spanish,
+    #[serde(rename = "it")]
+    #[value(name = "it")]
+    Italian,
+}
+
+/// How much text is looked at to detect the language
+pub const DETECTION_SAMPLE: usize = 16 * 1024;
+
+impl Language {
+    pub const ALL: [Language; 6] = [
+        Language::English,
+        Language::Romanian,
+        Language::French,
+        Language::German,
+        Language::Spanish,
+        Language::Italian,
+    ];
+
+    /// Detect the language of `text` from its character trigrams,
+    /// `None` if it isn't reliable or not one of the supported languages
+    pub fn detect(text: &str) -> Option<Self> {
+        let info = whatlang::detect(text)?;
+        if !info.is_reliable() {
+            return None;
+        }
+        match info.lang() {
+            whatlang::Lang::Eng => Some(Language::English),
+            whatlang::Lang::Ron => Some(Language::Romanian),
+            whatlang::Lang::Fra => Some(Language::French),
+            whatlang::Lang::Deu => Some(Language::German),
+            whatlang::Lang::Spa => Some(Language::Spanish),
+            whatlang::Lang::Ita => Some(Language::Italian),
+            _ => None,
+        }
+    }
+
+    /// The built-in stopword list, parsed once
+    pub fn stopwords(self) -> &'static WordFilter {
+        static FILTERS: [OnceLock<WordFilter>; 6] = [const { OnceLock::new() }; 6];
+        FILTERS[self as usize].get_or_init(|| {
+            let json = match self {
+                Language::English => include_str!("../stop_words.json"),
+                Language::Romanian => include_str!("../stopwords/ro.json"),
+                Language::French => include_str!("../stopwords/fr.json"),
+                Language::German => include_str!("../stopwords/de.json"),
+                Language::Spanish => include_str!("../stopwords/es.json"),
+                Language::Italian => include_str!("../stopwords/it.json"),
+            };
+            // the embedded lists are valid json, checked by the tests
+            serde_json::from_str(json).unwrap()
+        })
+    }
+
+    /// The Snowball stemmer for this language
+    pub fn stemmer(self) -> Algorithm {
+        match self {
+            Language::English => Algorithm::English,
+            Language::Romanian => Algorithm::Romanian,
+            Language::French => Algorithm::French,
+            Language::German => Algorithm::German,
+            Language::Spanish => Algorithm::Spanish,
+            Language::Italian => Algorithm::Italian,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builtin_lists_parse() {
+        let and = ["and", "și", "et", "und", "y", "e"];
+        for (language, and) in Language::ALL.into_iter().zip(and) {
+            assert!(language.stopwords().contains(and));
+        }
+    }
+
+    #[test]
+    fn detects_languages() {
+        let samples = [
+            (
+                include_str!("../metamorphosis.txt").get(..2000).unwrap(),
+                Language::English,
+            ),
+            (
+                "Aplicație de analiză a frecvenței cuvintelor, care primește un text și returnează informațiile procesate în funcție de opțiunile cu care a fost apelată.",
+                Language::Romanian,
+            ),
+            (
+                "Aujourd'hui, maman est morte. Ou peut-être hier, je ne sais pas. J'ai reçu un télégramme de l'asile.",
+                Language::French,
+            ),
+            (
+                "Als Gregor Samsa eines Morgens aus unruhigen Träumen erwachte, fand er sich in seinem Bett zu einem ungeheueren Ungeziefer verwandelt.",
+                Language::German,
+            ),
+        ];
+        for (text, language) in samples {
+            assert_eq!(Language::detect(text), Some(language));
+        }
+    }
+}
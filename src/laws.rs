@@ -0,0 +1,145 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::data::WordProcessor;
+
+/// Growth factor between two points of the vocabulary growth curve
+const GROWTH_STEP: f64 = 1.1;
+
+/// Vocabulary size after reading some words
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct GrowthPoint {
+    /// Counted words read so far
+    pub tokens: usize,
+    /// Distinct words among them
+    pub types: usize,
+}
+
+/// Sample the vocabulary growth curve at geometrically spaced points, plus the end of the text
+///
+/// `firsts` holds the index of the first occurrence of every word
+pub(crate) fn vocabulary_growth(mut firsts: Vec<usize>, total_words: usize) -> Vec<GrowthPoint> {
+    firsts.sort_unstable();
+    let mut points = Vec::new();
+    let mut tokens = 1;
+    while tokens < total_words {
+        points.push(GrowthPoint {
+            tokens,
+            types: firsts.partition_point(|&first| first < tokens),
+        });
+        tokens = ((tokens as f64 * GROWTH_STEP) as usize).max(tokens + 1);
+    }
+    if total_words > 0 {
+        points.push(GrowthPoint {
+            tokens: total_words,
+            types: firsts.len(),
+        });
+    }
+    points
+}
+
+/// Least squares fit of `y = coefficient * x^exponent` on a log-log scale
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct PowerLaw {
+    pub coefficient: f64,
+    pub exponent: f64,
+    /// Goodness of fit of the log-log regression
+    pub r_squared: f64,
+}
+
+impl PowerLaw {
+    /// `None` with fewer than two distinct x values
+    pub fn fit(points: impl IntoIterator<Item = (f64, f64)>) -> Option<Self> {
+        let points = points
+            .into_iter()
+            .map(|(x, y)| (x.ln(), y.ln()))
+            .collect::<Vec<_>>();
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let sxx = points
+            .iter()
+            .map(|(x, _)| (x - mean_x).powi(2))
+            .sum::<f64>();
+        let syy = points
+            .iter()
+            .map(|(_, y)| (y - mean_y).powi(2))
+            .sum::<f64>();
+        let sxy = points
+            .iter()
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum::<f64>();
+        if points.len() < 2 || sxx == 0.0 {
+            return None;
+        }
+        let exponent = sxy / sxx;
+        Some(Self {
+            coefficient: (mean_y - exponent * mean_x).exp(),
+            exponent,
+            // a flat line fits perfectly
+            r_squared: if syy == 0.0 {
+                1.0
+            } else {
+                sxy * sxy / (sxx * syy)
+            },
+        })
+    }
+}
+
+/// Zipf's and Heaps' law fitted to a text
+#[derive(Serialize)]
+pub struct Laws {
+    /// frequency = C * rank^-s, the Zipf exponent s is `-exponent`
+    pub zipf: Option<PowerLaw>,
+    /// types = K * tokens^β
+    pub heaps: Option<PowerLaw>,
+}
+
+impl Laws {
+    pub fn new(processor: &WordProcessor) -> Self {
+        Self {
+            zipf: PowerLaw::fit(
+                rank_frequency(processor).map(|(rank, frequency)| (rank as f64, frequency as f64)),
+            ),
+            heaps: PowerLaw::fit(
+                processor
+                    .vocabulary_growth
+                    .iter()
+                    .map(|point| (point.tokens as f64, point.types as f64)),
+            ),
+        }
+    }
+}
+
+/// (rank, frequency) of every word, starting at rank 1
+pub fn rank_frequency(processor: &WordProcessor) -> impl Iterator<Item = (usize, usize)> + '_ {
+    processor
+        .words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| (i + 1, word.count))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exact_power_laws() {
+        let zipf = PowerLaw::fit((1..=50).map(|rank| (rank as f64, 1000.0 / rank as f64))).unwrap();
+        assert!((zipf.exponent + 1.0).abs() < 1e-9);
+        assert!((zipf.coefficient - 1000.0).abs() < 1e-6);
+        assert!((zipf.r_squared - 1.0).abs() < 1e-9);
+
+        // every word is new
+        let growth = vocabulary_growth((0..100).collect(), 100);
+        assert!(growth.iter().all(|point| point.tokens == point.types));
+        assert_eq!(
+            growth.last(),
+            Some(&GrowthPoint {
+                tokens: 100,
+                types: 100
+            })
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! Word frequency analysis
+//!
+//! The [`Analyzer`] builder turns a text into a [`WordProcessor`] holding
+//! the frequency tables and diversity statistics of that text.
+pub mod analyzer;
+pub mod collocation;
+pub mod compare;
+pub mod concordance;
+pub mod counts;
+pub mod data;
+pub mod dispersion;
+pub mod diversity;
+pub mod error;
+pub mod format;
+pub mod index;
+pub mod input;
+pub mod keyness;
+pub mod language;
+pub mod laws;
+pub mod normalize;
+pub mod readability;
+pub mod schema;
+pub mod sentence;
+pub mod stream;
+pub mod tfidf;
+pub mod tokenizer;
+
+pub use analyzer::Analyzer;
+pub use compare::Comparison;
+pub use concordance::Concordance;
+pub use counts::CountTable;
+pub use data::{Metadata, TieBreak, WordData, WordFilter, WordProcessor};
+pub use dispersion::Dispersion;
+pub use diversity::Diversity;
+pub use error::{Error, Result};
+pub use format::OutputFormat;
+pub use index::Index;
+pub use input::{Source, SourceWalker};
+pub use keyness::Keyness;
+pub use language::Language;
+pub use laws::Laws;
+pub use normalize::{LemmaDictionary, Normalizer, Stemmer};
+pub use readability::Readability;
+pub use sentence::Structure;
+pub use stream::StreamAnalyzer;
+pub use tfidf::TfIdf;
+pub use tokenizer::{Tokenizer, TokenizerKind};
@@ -1,19 +1,25 @@
-use std::time::Instant;
+use std::{process::ExitCode, time::Instant};
 
 use clap::Parser;
 
 use crate::commands::Commands;
 
 mod commands;
-mod data;
 
-fn main() {
+fn main() -> ExitCode {
     let commands = Commands::parse();
     let now = Instant::now();
 
-    commands.handle_commands();
+    if let Err(e) = commands.handle_commands() {
+        eprintln!("{e:#}");
+        return ExitCode::FAILURE;
+    }
 
-    println!("processing finished after {} ms", now.elapsed().as_millis());
+    // the schema, completions and man pages are printed alone, so they can be redirected
+    if commands.timed() {
+        println!("processing finished after {} ms", now.elapsed().as_millis());
+    }
+    ExitCode::SUCCESS
 }
 
 #[cfg(test)]
@@ -25,4 +31,17 @@ mod test {
         use clap::CommandFactory;
         Commands::command().debug_assert();
     }
+
+    #[test]
+    fn subcommands_reject_foreign_flags() {
+        use clap::Parser;
+        assert!(Commands::try_parse_from(["wordfreq", "cloud", "a.txt", "-w", "20"]).is_ok());
+        assert!(Commands::try_parse_from(["wordfreq", "cloud", "a.txt", "--tfidf"]).is_err());
+    }
+
+    #[test]
+    fn default_filter_parses() {
+        let filter = wordfreq::WordFilter::english();
+        assert!(filter.contains("the"));
+    }
 }
@@ -0,0 +1,97 @@
+use std::{borrow::Cow, collections::HashMap, fs::File, io::BufReader, path::Path};
+
+pub use rust_stemmers::Algorithm;
+
+use crate::error::Result;
+
+/// Maps a (lowercase) word to the form it is counted under
+///
+/// Applied after stopword filtering, so stopword lists keep matching surface forms
+pub trait Normalizer: Send + Sync {
+    fn normalize<'w>(&self, word: &'w str) -> Cow<'w, str>;
+}
+
+/// Snowball stemmer, "runs" and "running" both become "run"
+pub struct Stemmer(rust_stemmers::Stemmer);
+
+impl Stemmer {
+    pub fn new(algorithm: Algorithm) -> Self {
+        Self(rust_stemmers::Stemmer::create(algorithm))
+    }
+
+    /// The english (Porter2) stemmer
+    pub fn english() -> Self {
+        Self::new(Algorithm::English)
+    }
+}
+
+impl Normalizer for Stemmer {
+    fn normalize<'w>(&self, word: &'w str) -> Cow<'w, str> {
+        self.0.stem(word)
+    }
+}
+
+/// Lemma dictionary, words missing from it are kept as they are
+///
+/// Loaded from a JSON object mapping forms to lemmas: `{"ran": "run", "mice": "mouse"}`
+pub struct LemmaDictionary(HashMap<String, String>);
+
+impl LemmaDictionary {
+    /// Parse the JSON object stored at `path`
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let forms: HashMap<String, String> =
+            serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        Ok(forms.into_iter().collect())
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for LemmaDictionary {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        // words are lowercased before normalizing, and counted lowercase after
+        Self(
+            iter.into_iter()
+                .map(|(form, lemma)| (form.as_ref().to_lowercase(), lemma.as_ref().to_lowercase()))
+                .collect(),
+        )
+    }
+}
+
+impl Normalizer for LemmaDictionary {
+    fn normalize<'w>(&self, word: &'w str) -> Cow<'w, str> {
+        match self.0.get(word) {
+            Some(lemma) => Cow::Owned(lemma.clone()),
+            None => Cow::Borrowed(word),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Analyzer;
+
+    #[test]
+    fn forms_are_merged() {
+        let text = "run runs running ran runner";
+        let stemmed = Analyzer::new()
+            .normalizer(Some(Box::new(Stemmer::english())))
+            .analyze(text)
+            .unwrap();
+        assert_eq!(stemmed.words[0].text, "run");
+        assert_eq!(stemmed.words[0].count, 3);
+        assert_eq!(
+            stemmed.words[0].forms.keys().collect::<Vec<_>>(),
+            ["run", "running", "runs"]
+        );
+
+        let lemmas = LemmaDictionary::from_iter([("Ran", "Run"), ("runs", "run")]);
+        let lemmatized = Analyzer::new()
+            .normalizer(Some(Box::new(lemmas)))
+            .analyze(text)
+            .unwrap();
+        assert_eq!(lemmatized.words[0].text, "run");
+        assert_eq!(lemmatized.words[0].count, 3);
+        // untouched words don't list their forms
+        assert!(lemmatized.words[1].forms.is_empty());
+    }
+}
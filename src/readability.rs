@@ -0,0 +1,130 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Syllables of a word at which it counts as complex (Gunning fog) or polysyllabic (SMOG)
+pub const POLYSYLLABLE: usize = 3;
+
+/// Per word counts behind the readability formulas, stopwords included
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TextStats {
+    words: usize,
+    letters: usize,
+    syllables: usize,
+    polysyllables: usize,
+}
+
+impl TextStats {
+    pub(crate) fn push(&mut self, word: &str) {
+        let syllables = syllables(word);
+        self.words += 1;
+        self.letters += word.chars().filter(|c| c.is_alphabetic()).count();
+        self.syllables += syllables;
+        if syllables >= POLYSYLLABLE {
+            self.polysyllables += 1;
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: TextStats) {
+        self.words += other.words;
+        self.letters += other.letters;
+        self.syllables += other.syllables;
+        self.polysyllables += other.polysyllables;
+    }
+}
+
+/// Standard readability formulas, calibrated on english text
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Readability {
+    pub sentences: usize,
+    /// Every word, stopwords included
+    pub words: usize,
+    /// Estimated from the vowel groups of every word
+    pub syllables: usize,
+    /// Words of 3 syllables or more
+    pub polysyllables: usize,
+    /// Alphabetic characters of every word
+    pub letters: usize,
+    /// 0-100, higher is easier
+    pub flesch_reading_ease: f64,
+    /// US school grade
+    pub flesch_kincaid_grade: f64,
+    /// Years of schooling
+    pub gunning_fog: f64,
+    /// Years of schooling, meant for 30 sentences or more
+    pub smog: f64,
+    /// US school grade, from letters instead of syllables
+    pub coleman_liau: f64,
+}
+
+impl Readability {
+    pub(crate) fn new(stats: &TextStats, sentences: usize) -> Self {
+        let words = stats.words.max(1) as f64;
+        let sentences_f = sentences.max(1) as f64;
+        let words_per_sentence = words / sentences_f;
+        let syllables_per_word = stats.syllables as f64 / words;
+        let polysyllables = stats.polysyllables as f64;
+        Self {
+            sentences,
+            words: stats.words,
+            syllables: stats.syllables,
+            polysyllables: stats.polysyllables,
+            letters: stats.letters,
+            flesch_reading_ease: 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word,
+            flesch_kincaid_grade: 0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59,
+            gunning_fog: 0.4 * (words_per_sentence + 100.0 * polysyllables / words),
+            smog: 1.0430 * (polysyllables * 30.0 / sentences_f).sqrt() + 3.1291,
+            coleman_liau: 0.0588 * (100.0 * stats.letters as f64 / words)
+                - 0.296 * (100.0 * sentences_f / words)
+                - 15.8,
+        }
+    }
+}
+
+/// Estimate the syllables of a lowercase word from its vowel groups
+///
+/// A final silent "e" ("make", but not "table") isn't counted, every word has at least one
+pub fn syllables(word: &str) -> usize {
+    let chars = word.chars().collect::<Vec<_>>();
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for &c in &chars {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    if let [.., before, 'e'] = chars[..] {
+        let consonant_le = before == 'l' && chars.len() > 2 && !is_vowel(chars[chars.len() - 3]);
+        if !is_vowel(before) && !consonant_le && count > 1 {
+            count -= 1;
+        }
+    }
+    count.max(1)
+}
+
+fn is_vowel(c: char) -> bool {
+    "aeiouyàáâãäåæèéêëìíîïòóôõöøœùúûüýÿăāēīōū".contains(c)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Analyzer;
+
+    #[test]
+    fn sentences_and_syllables() {
+        let counted = ["cat", "make", "table", "reading", "beautiful", "university"].map(syllables);
+        assert_eq!(counted, [1, 1, 2, 2, 3, 5]);
+
+        let text = "The cat sat. It was 3.14 meters tall! \"Really?\" Yes";
+        let processor = Analyzer::new().analyze(text).unwrap();
+        assert_eq!(processor.readability.sentences, 4);
+        assert_eq!(processor.readability.words, 9);
+
+        // digits kept by other tokenizers are no letters
+        let mut stats = TextStats::default();
+        stats.push("3rd");
+        assert_eq!(stats.letters, 2);
+    }
+}
@@ -0,0 +1,75 @@
+use schemars::JsonSchema;
+use serde_json::{Value, json};
+
+use crate::{
+    compare::Comparison,
+    data::{FORMAT_VERSION, WordProcessor},
+    tfidf::TfIdf,
+};
+
+/// The JSON documents written by the command line tool
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Document {
+    /// An analysis, [`WordProcessor`]
+    #[default]
+    Analysis,
+    /// A comparison of texts, [`Comparison`]
+    Comparison,
+    /// A TF-IDF ranking, [`TfIdf`]
+    Tfidf,
+}
+
+impl Document {
+    /// The JSON Schema of the document, generated from its type
+    ///
+    /// The published copies are in `schema/`, their `version` is pinned to [`FORMAT_VERSION`]
+    pub fn schema(self) -> Value {
+        match self {
+            Document::Analysis => versioned::<WordProcessor>("wordfreq analysis"),
+            Document::Comparison => versioned::<Comparison>("wordfreq comparison"),
+            Document::Tfidf => versioned::<TfIdf>("wordfreq tf-idf ranking"),
+        }
+    }
+}
+
+// the schema of `T`, with the version written now
+fn versioned<T: JsonSchema>(title: &str) -> Value {
+    let mut schema = schemars::schema_for!(T).to_value();
+    schema["title"] = json!(title);
+    // what is written now, the default is only there to detect old files
+    let version = &mut schema["properties"]["version"];
+    version["const"] = json!(FORMAT_VERSION);
+    if let Some(version) = version.as_object_mut() {
+        version.remove("default");
+    }
+    if let Some(required) = schema["required"].as_array_mut() {
+        required.retain(|field| field != "version");
+        required.insert(0, json!("version"));
+    }
+    schema
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn published_schemas_are_current() {
+        // regenerate with `wordfreq schema <DOCUMENT> > schema/<document>.schema.json`
+        let published = [
+            (
+                Document::Analysis,
+                include_str!("../schema/analysis.schema.json"),
+            ),
+            (
+                Document::Comparison,
+                include_str!("../schema/comparison.schema.json"),
+            ),
+            (Document::Tfidf, include_str!("../schema/tfidf.schema.json")),
+        ];
+        for (document, published) in published {
+            let published: Value = serde_json::from_str(published).unwrap();
+            assert_eq!(published, document.schema(), "{document:?}");
+        }
+    }
+}
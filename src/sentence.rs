@@ -0,0 +1,274 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Characters ending a sentence
+const TERMINATORS: &[char] = &['.', '!', '?', '…'];
+/// Characters that may follow a terminator without cancelling it: `end." Next`
+const CLOSING: &[char] = &['"', '\'', '”', '’', '»', ')', ']'];
+/// Words whose period doesn't end a sentence
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "vs", "etc", "cf", "al", "fig", "vol", "pp",
+    "approx", "dept", "inc", "ltd", "co", "corp", "gen", "col", "lt", "capt", "sgt", "rev", "hon",
+    "mt", "ave", "jan", "feb", "mar", "apr", "jun", "jul", "aug", "sep", "sept", "oct", "nov",
+    "dec",
+];
+/// Common first words of a sentence, an initial is never followed by them: `Plan B. The end`
+const SENTENCE_OPENERS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "he", "her", "his", "i", "if", "in", "it", "its", "my",
+    "no", "on", "our", "she", "so", "that", "the", "their", "then", "there", "they", "this", "we",
+    "what", "when", "you",
+];
+
+/// Running sentence and paragraph segmentation of a token stream
+///
+/// Only the text between the tokens is looked at:
+/// - a sentence ends at a terminator followed (after any closing quotes or brackets)
+///   by whitespace, so "3.14" and "U.S" don't split anything
+/// - a period after an abbreviation doesn't end a sentence, neither does one after
+///   an initial (a single letter other than "I") unless a common sentence opener follows,
+///   or after "No" followed by a number: `No. 5`
+/// - neither does a terminator followed by a lowercase word: `"Really?" she asked`
+/// - a paragraph (and its last sentence) ends at a blank line
+///
+/// The state carries over between chunks, so a text gives the same sentences however it is split
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Segmenter {
+    // a terminator was seen and only closing characters since
+    terminator: bool,
+    // the terminator was a period after an initial or "no", the next word decides
+    tentative: bool,
+    // the current sentence ended, the next word starts a new one
+    boundary: bool,
+    // newlines in the current run of whitespace
+    newlines: usize,
+    // a blank line was seen, the next word starts a new paragraph
+    paragraph_break: bool,
+    // last word of the current sentence
+    last_word: String,
+    sentence_words: usize,
+    paragraph_words: usize,
+    paragraph_sentences: usize,
+    // words in every finished sentence
+    sentences: Vec<usize>,
+    // (words, sentences) of every finished paragraph
+    paragraphs: Vec<(usize, usize)>,
+}
+
+impl Segmenter {
+    /// Scan the text between two tokens, `next` is the following token as written
+    /// or `None` if it isn't known yet (the end of a chunk)
+    pub(crate) fn gap(&mut self, gap: &str, next: Option<&str>) {
+        for (i, c) in gap.char_indices() {
+            if c == '\n' {
+                self.newlines += 1;
+                if self.newlines >= 2 {
+                    self.paragraph_break = true;
+                }
+            } else if !c.is_whitespace() {
+                self.newlines = 0;
+            }
+
+            if TERMINATORS.contains(&c) {
+                if c != '.' || !ABBREVIATIONS.contains(&self.last_word.as_str()) {
+                    self.terminator = true;
+                    self.tentative = c == '.' && self.is_tentative();
+                }
+            } else if c.is_whitespace() {
+                if self.terminator {
+                    self.boundary = true;
+                }
+                self.terminator = false;
+            } else if !CLOSING.contains(&c) {
+                // text the tokenizer skips, eg. the number of `No. 5`
+                if self.boundary && self.tentative && self.continues(&gap[i..]) {
+                    self.boundary = false;
+                }
+                self.terminator = false;
+                self.tentative = false;
+            }
+        }
+        let lowercase = next
+            .and_then(|next| next.chars().next())
+            .is_some_and(char::is_lowercase);
+        let abbreviation = self.tentative && next.is_some_and(|next| self.continues(next));
+        if (lowercase || abbreviation) && !self.paragraph_break {
+            self.boundary = false;
+        }
+    }
+
+    /// Count the next (lowercase) word
+    pub(crate) fn word(&mut self, word: &str) {
+        if self.boundary || self.paragraph_break {
+            self.close_sentence();
+        }
+        if self.paragraph_break {
+            self.close_paragraph();
+        }
+        self.terminator = false;
+        self.tentative = false;
+        self.newlines = 0;
+        self.sentence_words += 1;
+        self.paragraph_words += 1;
+        word.clone_into(&mut self.last_word);
+    }
+
+    /// The end of a document always ends its last sentence and paragraph
+    pub(crate) fn end_document(&mut self) {
+        self.close_sentence();
+        self.close_paragraph();
+        self.terminator = false;
+        self.newlines = 0;
+    }
+
+    pub(crate) fn merge(&mut self, mut other: Segmenter) {
+        self.end_document();
+        other.end_document();
+        self.sentences.extend(other.sentences);
+        self.paragraphs.extend(other.paragraphs);
+    }
+
+    /// Sentences so far, the unfinished one included
+    pub(crate) fn sentences(&self) -> usize {
+        self.sentences.len() + usize::from(self.sentence_words > 0)
+    }
+
+    // whether a period after the last word may be an abbreviation's
+    fn is_tentative(&self) -> bool {
+        let mut chars = self.last_word.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => c.is_alphabetic() && c != 'i',
+            _ => self.last_word == "no",
+        }
+    }
+
+    // whether the tentative period is an abbreviation's, given the text after it
+    fn continues(&self, next: &str) -> bool {
+        if self.last_word == "no" {
+            return next.starts_with(|c: char| c.is_ascii_digit());
+        }
+        !SENTENCE_OPENERS.contains(&next.to_lowercase().as_str())
+    }
+
+    fn close_sentence(&mut self) {
+        if self.sentence_words > 0 {
+            self.sentences.push(self.sentence_words);
+            self.paragraph_sentences += 1;
+        }
+        self.sentence_words = 0;
+        self.boundary = false;
+        self.last_word.clear();
+    }
+
+    fn close_paragraph(&mut self) {
+        if self.paragraph_words > 0 {
+            self.paragraphs
+                .push((self.paragraph_words, self.paragraph_sentences));
+        }
+        self.paragraph_words = 0;
+        self.paragraph_sentences = 0;
+        self.paragraph_break = false;
+    }
+}
+
+/// Summary of a list of lengths
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Distribution {
+    /// Arithmetic mean
+    pub mean: f64,
+    pub median: f64,
+    pub min: usize,
+    pub max: usize,
+    /// Population standard deviation
+    pub std_dev: f64,
+}
+
+impl Distribution {
+    pub fn new(values: &[usize]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_unstable();
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<usize>() as f64 / n;
+        let middle = sorted.len() / 2;
+        Self {
+            mean,
+            median: if sorted.len().is_multiple_of(2) {
+                (sorted[middle - 1] + sorted[middle]) as f64 / 2.0
+            } else {
+                sorted[middle] as f64
+            },
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            std_dev: (values
+                .iter()
+                .map(|&value| (value as f64 - mean).powi(2))
+                .sum::<f64>()
+                / n)
+                .sqrt(),
+        }
+    }
+}
+
+/// Sentence and paragraph statistics, every word (stopwords included) counts
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Structure {
+    pub sentences: usize,
+    pub paragraphs: usize,
+    /// Words per sentence
+    pub sentence_length: Distribution,
+    /// Words per paragraph
+    pub paragraph_length: Distribution,
+    /// Sentences per paragraph
+    pub paragraph_sentences: Distribution,
+    /// Index (from 0) of the longest sentence
+    pub longest_sentence: usize,
+    /// Words in every sentence, in order
+    pub sentence_lengths: Vec<usize>,
+}
+
+impl Structure {
+    pub(crate) fn new(mut segmenter: Segmenter) -> Self {
+        // count the unfinished sentence and paragraph too
+        segmenter.end_document();
+        let (words, sentences): (Vec<_>, Vec<_>) = segmenter.paragraphs.iter().copied().unzip();
+        let longest_sentence = segmenter
+            .sentences
+            .iter()
+            .enumerate()
+            .max_by_key(|&(i, &length)| (length, std::cmp::Reverse(i)))
+            .map_or(0, |(i, _)| i);
+        Self {
+            sentences: segmenter.sentences.len(),
+            paragraphs: segmenter.paragraphs.len(),
+            sentence_length: Distribution::new(&segmenter.sentences),
+            paragraph_length: Distribution::new(&words),
+            paragraph_sentences: Distribution::new(&sentences),
+            longest_sentence,
+            sentence_lengths: segmenter.sentences,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Analyzer;
+
+    #[test]
+    fn sentences_and_paragraphs() {
+        let text = "Mr. Smith met J. K. Rowling. \"Really?\" she asked.\n\nYes it was\n \nThe end";
+        let processor = Analyzer::new().analyze(text).unwrap();
+        let structure = processor.structure;
+        assert_eq!(structure.sentence_lengths, [6, 3, 3, 2]);
+        assert_eq!(structure.paragraphs, 3);
+        assert_eq!(structure.paragraph_sentences.max, 2);
+        assert_eq!(structure.longest_sentence, 0);
+
+        // "no", "I" and a letter before a sentence opener end their sentence
+        let text = "The answer was no. We left early. It was me, not you or I. They stayed. \
+            Plan B. The end came at No. 5 Main Street.";
+        let processor = Analyzer::new().analyze(text).unwrap();
+        assert_eq!(processor.structure.sentence_lengths, [4, 3, 7, 2, 2, 7]);
+    }
+}
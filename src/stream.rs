@@ -0,0 +1,256 @@
+use std::io::{self, BufRead};
+
+use crate::{
+    analyzer::Analyzer,
+    counts::CountTable,
+    data::WordProcessor,
+    error::Result,
+    language::{DETECTION_SAMPLE, Language},
+};
+
+/// Incremental analysis of a text read in chunks
+///
+/// Only the text up to the last whitespace of each chunk is tokenized, the
+/// rest is carried over so words split across chunks are counted once.
+/// Text without whitespace is carried over up to 1 MiB, then counted anyway
+/// (splitting a longer token), and the text of a tokenizer whose tokens may
+/// span whitespace (a custom regex pattern) is buffered and tokenized whole
+///
+/// ```
+/// use wordfreq::Analyzer;
+///
+/// let analyzer = Analyzer::new();
+/// let mut stream = analyzer.stream();
+/// stream.feed(b"the quick br").unwrap();
+/// stream.feed(b"own fox").unwrap();
+/// let processor = stream.finish().unwrap();
+/// assert_eq!(processor.total_words, 4);
+/// ```
+pub struct StreamAnalyzer<'a> {
+    analyzer: &'a Analyzer,
+    counts: CountTable,
+    chunker: Chunker,
+}
+
+impl<'a> StreamAnalyzer<'a> {
+    pub(crate) fn new(analyzer: &'a Analyzer) -> Self {
+        Self {
+            analyzer,
+            counts: analyzer.count_table(),
+            chunker: Chunker::default(),
+        }
+    }
+
+    /// Feed the next chunk of UTF-8 text
+    ///
+    /// # Errors
+    /// [`crate::Error::Io`] if the text isn't valid UTF-8
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<()> {
+        self.chunker.feed(self.analyzer, &mut self.counts, chunk)
+    }
+
+    /// Feed everything from `reader`
+    ///
+    /// # Errors
+    /// [`crate::Error::Io`] if reading fails or the text isn't valid UTF-8
+    pub fn read_from(&mut self, reader: impl BufRead) -> Result<()> {
+        self.chunker
+            .read_from(self.analyzer, &mut self.counts, reader)
+    }
+
+    /// Count the leftover text and build the statistics
+    ///
+    /// # Errors
+    /// [`crate::Error::Io`] if the leftover text isn't valid UTF-8,
+    /// [`crate::Error::EmptyInput`] if no words were counted
+    pub fn finish(mut self) -> Result<WordProcessor> {
+        self.chunker.flush(self.analyzer, &mut self.counts)?;
+        self.analyzer.finish(self.counts)
+    }
+}
+
+/// Splits incoming bytes at whitespace (line breaks for tokens that may span
+/// whitespace) and counts the complete part
+#[derive(Default)]
+pub(crate) struct Chunker {
+    pending: Vec<u8>,
+    // the start of pending is known to hold no split point, only the rest is searched
+    scanned: usize,
+    // the detected language, once enough text was seen
+    language: Option<Option<Language>>,
+}
+
+impl Chunker {
+    pub(crate) fn feed(
+        &mut self,
+        analyzer: &Analyzer,
+        counts: &mut CountTable,
+        chunk: &[u8],
+    ) -> Result<()> {
+        self.pending.extend_from_slice(chunk);
+        if self.language.is_none() {
+            // hold the text back until there's enough to detect the language
+            if analyzer.detects_language() && self.pending.len() < DETECTION_SAMPLE {
+                return Ok(());
+            }
+            self.detect(analyzer, counts);
+        }
+        if let Some(end) = self.complete_end(analyzer.splits_at_whitespace())? {
+            let text = std::str::from_utf8(&self.pending[..end]).map_err(invalid_data)?;
+            analyzer.count_with(text, counts, analyzer.filter_for(self.language.flatten()));
+            self.pending.drain(..end);
+        }
+        self.scanned = self.pending.len();
+        Ok(())
+    }
+
+    pub(crate) fn read_from(
+        &mut self,
+        analyzer: &Analyzer,
+        counts: &mut CountTable,
+        mut reader: impl BufRead,
+    ) -> Result<()> {
+        loop {
+            let chunk = reader.fill_buf()?;
+            if chunk.is_empty() {
+                return Ok(());
+            }
+            let len = chunk.len();
+            self.feed(analyzer, counts, chunk)?;
+            reader.consume(len);
+        }
+    }
+
+    // count whatever is left
+    pub(crate) fn flush(&mut self, analyzer: &Analyzer, counts: &mut CountTable) -> Result<()> {
+        if self.language.is_none() {
+            self.detect(analyzer, counts);
+        }
+        let text = std::str::from_utf8(&self.pending).map_err(invalid_data)?;
+        analyzer.count_with(text, counts, analyzer.filter_for(self.language.flatten()));
+        self.pending.clear();
+        self.scanned = 0;
+        Ok(())
+    }
+}
+
+// bytes without a split point held back before they're counted anyway
+const MAX_PENDING: usize = 1 << 20;
+
+impl Chunker {
+    // end of the text that can be counted now: up to the last whitespace (line
+    // break if tokens may span whitespace), or once too much text is held up to
+    // the last whitespace or whole character
+    fn complete_end(&self, splits_at_whitespace: bool) -> Result<Option<usize>> {
+        let split: fn(char) -> bool = if splits_at_whitespace {
+            char::is_whitespace
+        } else {
+            |c| c == '\n'
+        };
+        // from the start of the character the unsearched bytes begin in
+        let mut start = self.scanned.min(self.pending.len());
+        while start > 0 && start < self.pending.len() && self.pending[start] & 0xC0 == 0x80 {
+            start -= 1;
+        }
+        if let Some(end) = end_after(valid_prefix(&self.pending[start..])?, split) {
+            return Ok(Some(start + end));
+        }
+        if self.pending.len() < MAX_PENDING {
+            return Ok(None);
+        }
+        let text = valid_prefix(&self.pending)?;
+        Ok((!text.is_empty()).then(|| end_after(text, char::is_whitespace).unwrap_or(text.len())))
+    }
+
+    // detect the language on the text buffered so far
+    fn detect(&mut self, analyzer: &Analyzer, counts: &mut CountTable) {
+        let text = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text,
+            // the sample may end in the middle of a character
+            Err(e) => std::str::from_utf8(&self.pending[..e.valid_up_to()]).unwrap_or_default(),
+        };
+        self.language = Some(analyzer.detect(text, counts));
+    }
+}
+
+// the text up to the last whole character, the bytes may end in the middle of one
+fn valid_prefix(bytes: &[u8]) -> Result<&str> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(e) if e.error_len().is_none() => {
+            Ok(std::str::from_utf8(&bytes[..e.valid_up_to()]).map_err(invalid_data)?)
+        }
+        Err(e) => Err(invalid_data(e).into()),
+    }
+}
+
+// the end of the last character of `text` matching `split`
+fn end_after(text: &str, split: fn(char) -> bool) -> Option<usize> {
+    text.char_indices()
+        .rfind(|&(_, c)| split(c))
+        .map(|(i, c)| i + c.len_utf8())
+}
+
+fn invalid_data(e: std::str::Utf8Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Analyzer, TokenizerKind, WordFilter};
+
+    #[test]
+    fn chunked_matches_whole() {
+        let text = include_str!("../metamorphosis.txt");
+        let analyzer = Analyzer::new()
+            .filter(WordFilter::english())
+            .ngram_sizes([2, 3, 4]);
+        let whole = analyzer.analyze(text).unwrap();
+
+        // tiny chunks split words and multi byte characters
+        let mut stream = analyzer.stream();
+        for chunk in text.as_bytes().chunks(7) {
+            stream.feed(chunk).unwrap();
+        }
+        let streamed = stream.finish().unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&whole).unwrap(),
+            serde_json::to_value(&streamed).unwrap()
+        );
+    }
+
+    #[test]
+    fn unusual_whitespace_streams() {
+        // no ascii whitespace, the pending text is still bounded
+        let analyzer = Analyzer::new();
+        let mut stream = analyzer.stream();
+        for _ in 0..100_000 {
+            stream.feed("word\u{3000}".as_bytes()).unwrap();
+            assert!(stream.chunker.pending.len() < 16);
+        }
+        assert_eq!(stream.finish().unwrap().total_words, 100_000);
+
+        // tokens spanning whitespace are counted as in the whole text, lines are still streamed
+        let text = "the quick brown fox\njumps over the lazy dog\n";
+        let analyzer =
+            Analyzer::new().tokenizer(TokenizerKind::Regex.build(Some(r"\w+ \w+")).unwrap());
+        let mut stream = analyzer.stream();
+        for chunk in text.as_bytes().chunks(3) {
+            stream.feed(chunk).unwrap();
+            assert!(stream.chunker.pending.len() < 24);
+        }
+        assert_eq!(
+            serde_json::to_value(analyzer.analyze(text).unwrap()).unwrap(),
+            serde_json::to_value(stream.finish().unwrap()).unwrap()
+        );
+
+        // a single line is held up to the cap
+        let mut stream = analyzer.stream();
+        for _ in 0..300_000 {
+            stream.feed(b"word ").unwrap();
+            assert!(stream.chunker.pending.len() <= super::MAX_PENDING);
+        }
+        assert_eq!(stream.finish().unwrap().total_words, 150_000);
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{FORMAT_VERSION, Metadata, WordProcessor};
+
+/// Term frequency weighting
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum TfWeight {
+    /// Raw count
+    Raw,
+    /// 1 + ln(count)
+    #[default]
+    Log,
+    /// Count divided by the document length
+    Relative,
+    /// 0.5 + 0.5 * count / highest count in the document
+    Augmented,
+}
+
+/// Inverse document frequency weighting, for N documents of which df contain the term
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum IdfWeight {
+    /// ln(N / df)
+    Standard,
+    /// ln((1 + N) / (1 + df)) + 1, terms in every document still count
+    #[default]
+    Smooth,
+    /// max(0, ln((N - df) / df))
+    Probabilistic,
+}
+
+impl TfWeight {
+    fn weight(self, count: usize, length: usize, max: usize) -> f64 {
+        let count = count as f64;
+        match self {
+            TfWeight::Raw => count,
+            TfWeight::Log => 1.0 + count.ln(),
+            TfWeight::Relative => count / length as f64,
+            TfWeight::Augmented => 0.5 + 0.5 * count / max as f64,
+        }
+    }
+}
+
+impl IdfWeight {
+    fn weight(self, documents: usize, df: usize) -> f64 {
+        let (n, df) = (documents as f64, df as f64);
+        match self {
+            IdfWeight::Standard => (n / df).ln(),
+            IdfWeight::Smooth => ((1.0 + n) / (1.0 + df)).ln() + 1.0,
+            IdfWeight::Probabilistic => ((n - df) / df).ln().max(0.0),
+        }
+    }
+}
+
+/// TF-IDF score of a term in one document
+#[derive(Serialize, JsonSchema)]
+pub struct TermScore {
+    pub text: String,
+    /// Occurrences in the document
+    pub count: usize,
+    /// Documents containing the term
+    pub document_frequency: usize,
+    pub score: f64,
+}
+
+/// The best scoring terms of one document
+#[derive(Serialize, JsonSchema)]
+pub struct DocumentTerms {
+    pub name: String,
+    /// Best score first
+    pub terms: Vec<TermScore>,
+}
+
+/// TF-IDF ranking of the terms of every document in a collection
+#[derive(Serialize, JsonSchema)]
+pub struct TfIdf {
+    /// Format version of the ranking ([`FORMAT_VERSION`])
+    pub version: u32,
+    /// Where and how the documents were analyzed, set by the command line tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    pub tf: TfWeight,
+    pub idf: IdfWeight,
+    pub documents: Vec<DocumentTerms>,
+}
+
+impl TfIdf {
+    /// Score the terms of every document, keeping the `limit` best of each
+    pub fn new(
+        documents: &[(String, &WordProcessor)],
+        tf: TfWeight,
+        idf: IdfWeight,
+        limit: usize,
+    ) -> Self {
+        let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+        for (_, processor) in documents {
+            for word in &processor.words {
+                *document_frequency.entry(&word.text).or_default() += 1;
+            }
+        }
+
+        let documents = documents
+            .iter()
+            .map(|(name, processor)| {
+                // the words are sorted, so the first one is the most frequent
+                let max = processor.words.first().map_or(1, |word| word.count);
+                let mut terms = processor
+                    .words
+                    .iter()
+                    .map(|word| {
+                        let df = document_frequency[word.text.as_str()];
+                        TermScore {
+                            text: word.text.clone(),
+                            count: word.count,
+                            document_frequency: df,
+                            score: tf.weight(word.count, processor.total_words, max)
+                                * idf.weight(documents.len(), df),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                // stable sort, ties keep the frequency order
+                terms.sort_by(|a, b| b.score.total_cmp(&a.score));
+                terms.truncate(limit);
+                DocumentTerms {
+                    name: name.clone(),
+                    terms,
+                }
+            })
+            .collect();
+
+        Self {
+            version: FORMAT_VERSION,
+            metadata: None,
+            tf,
+            idf,
+            documents,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Analyzer;
+
+    #[test]
+    fn common_terms_rank_low() {
+        let analyzer = Analyzer::new();
+        let a = analyzer.analyze("the the the cat").unwrap();
+        let b = analyzer.analyze("the the dog").unwrap();
+        let documents = [("a".to_string(), &a), ("b".to_string(), &b)];
+
+        let tfidf = TfIdf::new(&documents, TfWeight::Raw, IdfWeight::Standard, 10);
+        let a = &tfidf.documents[0].terms;
+        assert_eq!(a[0].text, "cat");
+        assert!((a[0].score - 2f64.ln()).abs() < 1e-9);
+        // in every document
+        assert_eq!(a[1].text, "the");
+        assert_eq!(a[1].score, 0.0);
+    }
+}
@@ -0,0 +1,199 @@
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::{Error, Result};
+
+/// Splits a text into word tokens
+///
+/// Tokens are returned as `(byte offset, token)` pairs in text order,
+/// the offset pointing at the start of the token inside `text`.
+pub trait Tokenizer: Send + Sync {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<(usize, &'t str)>;
+
+    /// Whether tokens never span whitespace, so streamed text can be split at it
+    ///
+    /// Streams of a tokenizer returning false are split at line breaks instead
+    fn splits_at_whitespace(&self) -> bool {
+        true
+    }
+}
+
+/// Splits on whitespace and common punctuation, keeping only purely alphabetic tokens
+///
+/// Tokens with anything else are dropped, like "don't" and "3rd"
+#[derive(Clone, Copy, Default)]
+pub struct SimpleTokenizer;
+
+impl SimpleTokenizer {
+    // common split particles
+    fn is_separator(c: char) -> bool {
+        c.is_whitespace()
+            || c == ','
+            || c == '.'
+            || c == '"'
+            || c == '!'
+            || c == '?'
+            || c == '-'
+            || c == '—'
+    }
+}
+
+impl Tokenizer for SimpleTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<(usize, &'t str)> {
+        let mut tokens = Vec::new();
+        let mut start = 0;
+        for (i, c) in text
+            .char_indices()
+            .chain(std::iter::once((text.len(), ' ')))
+        {
+            if Self::is_separator(c) {
+                let word = &text[start..i];
+                // only get the words that are alphabetic
+                if !word.is_empty() && word.chars().all(|c| c.is_alphabetic()) {
+                    tokens.push((start, word));
+                }
+                start = i + c.len_utf8();
+            }
+        }
+        tokens
+    }
+}
+
+/// Unicode (UAX #29) word boundaries
+///
+/// Keeps "don't", "co-operate" as two words, "U.S" and "3rd"
+#[derive(Clone, Copy, Default)]
+pub struct UnicodeTokenizer;
+
+impl Tokenizer for UnicodeTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<(usize, &'t str)> {
+        text.unicode_word_indices().collect()
+    }
+}
+
+/// Every match of a regular expression is a token
+///
+/// A custom pattern may match whitespace, so its streams are tokenized whole
+#[derive(Clone)]
+pub struct RegexTokenizer {
+    regex: Regex,
+    // only the default pattern is known to never match whitespace
+    splits_at_whitespace: bool,
+}
+
+impl RegexTokenizer {
+    /// Letters with inner apostrophes or hyphens, or numbers with an optional suffix
+    pub const DEFAULT_PATTERN: &str = r"\p{L}+(?:['’-]\p{L}+)*|\p{N}+\p{L}*";
+
+    pub fn new(pattern: &str) -> Result<Self> {
+        Ok(Self {
+            regex: Regex::new(pattern)?,
+            splits_at_whitespace: pattern == Self::DEFAULT_PATTERN,
+        })
+    }
+}
+
+impl Default for RegexTokenizer {
+    fn default() -> Self {
+        // the default pattern is a valid regex
+        Self::new(Self::DEFAULT_PATTERN).unwrap()
+    }
+}
+
+impl Tokenizer for RegexTokenizer {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<(usize, &'t str)> {
+        // patterns like `a*` also match the empty string between words
+        self.regex
+            .find_iter(text)
+            .filter(|m| !m.is_empty())
+            .map(|m| (m.start(), m.as_str()))
+            .collect()
+    }
+
+    fn splits_at_whitespace(&self) -> bool {
+        self.splits_at_whitespace
+    }
+}
+
+/// The built-in tokenizers
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenizerKind {
+    /// Whitespace and punctuation split, alphabetic tokens only
+    #[default]
+    Simple,
+    /// Unicode word boundaries
+    Unicode,
+    /// Regular expression matches
+    Regex,
+}
+
+impl TokenizerKind {
+    /// Build the tokenizer, only [`TokenizerKind::Regex`] takes a `pattern`
+    pub fn build(self, pattern: Option<&str>) -> Result<Box<dyn Tokenizer>> {
+        Ok(match (self, pattern) {
+            (TokenizerKind::Regex, Some(pattern)) => Box::new(RegexTokenizer::new(pattern)?),
+            (TokenizerKind::Regex, None) => Box::new(RegexTokenizer::default()),
+            (_, Some(_)) => return Err(Error::UnusedPattern(self)),
+            (TokenizerKind::Simple, None) => Box::new(SimpleTokenizer),
+            (TokenizerKind::Unicode, None) => Box::new(UnicodeTokenizer),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn words(tokenizer: &dyn Tokenizer, text: &str) -> Vec<String> {
+        tokenizer
+            .tokenize(text)
+            .into_iter()
+            .map(|(_, w)| w.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn builtin_tokenizers() {
+        let text = "Don't co-operate with the U.S. on the 3rd—ok?";
+        assert_eq!(
+            words(&SimpleTokenizer, text),
+            ["co", "operate", "with", "the", "U", "S", "on", "the", "ok"]
+        );
+        assert_eq!(
+            words(&UnicodeTokenizer, text),
+            [
+                "Don't", "co", "operate", "with", "the", "U.S", "on", "the", "3rd", "ok"
+            ]
+        );
+        assert_eq!(
+            words(&RegexTokenizer::default(), text),
+            [
+                "Don't",
+                "co-operate",
+                "with",
+                "the",
+                "U",
+                "S",
+                "on",
+                "the",
+                "3rd",
+                "ok"
+            ]
+        );
+        // a pattern only goes with the regex tokenizer
+        assert!(TokenizerKind::Simple.build(Some("[a-z]{4,}")).is_err());
+        assert!(TokenizerKind::Regex.build(Some("[a-z]{4,}")).is_ok());
+        // empty matches are no tokens
+        let tokenizer = TokenizerKind::Regex.build(Some("a*")).unwrap();
+        assert_eq!(words(tokenizer.as_ref(), "bbb ccc aa"), ["aa"]);
+        // offsets point back into the text
+        for (offset, word) in SimpleTokenizer.tokenize(text) {
+            assert_eq!(&text[offset..offset + word.len()], word);
+        }
+    }
+}